@@ -1,11 +1,15 @@
-use sc_service::ChainType;
+use pallet_dids::structs::{DIDSignature, GenesisDID};
+use sc_service::{config::MultiaddrWithPeerId, ChainType};
+use serde::Deserialize;
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
-use sp_core::{sr25519, OpaquePeerId, Pair, Public};
+use sp_core::{ed25519, hexdisplay::HexDisplay, sr25519, OpaquePeerId, Pair, Public};
 use sp_finality_grandpa::AuthorityId as GrandpaId;
 use sp_runtime::traits::{IdentifyAccount, Verify};
+use std::{convert::TryFrom, path::Path};
 use trackback_node_runtime::{
-	AccountId, AuraConfig, BalancesConfig, GenesisConfig, GrandpaConfig, NodeAuthorizationConfig,
-	Signature, SudoConfig, SystemConfig, WASM_BINARY,
+	opaque::SessionKeys, AccountId, AuraConfig, BalancesConfig, CouncilConfig, DIDModule,
+	DIDModuleConfig, DemocracyConfig, GenesisConfig, GrandpaConfig, NodeAuthorizationConfig,
+	SessionConfig, Signature, SudoConfig, SystemConfig, ValidatorSetConfig, WASM_BINARY,
 };
 
 // The URL for the telemetry server.
@@ -31,12 +35,256 @@ where
 	AccountPublic::from(get_from_seed::<TPublic>(seed)).into_account()
 }
 
-/// Generate an Aura authority key.
-pub fn authority_keys_from_seed(s: &str) -> (AuraId, GrandpaId) {
-	(get_from_seed::<AuraId>(s), get_from_seed::<GrandpaId>(s))
+/// Generate a validator account with its Aura and Grandpa session keys.
+pub fn authority_keys_from_seed(s: &str) -> (AccountId, AuraId, GrandpaId) {
+	(
+		get_account_id_from_seed::<sr25519::Public>(s),
+		get_from_seed::<AuraId>(s),
+		get_from_seed::<GrandpaId>(s),
+	)
 }
 
-pub fn development_config() -> Result<ChainSpec, String> {
+fn session_keys(aura: AuraId, grandpa: GrandpaId) -> SessionKeys {
+	SessionKeys { aura, grandpa }
+}
+
+/// Generate a DID from seed, its document is signed by the seed's ed25519 key and its controller
+/// is the seed's sr25519 account.
+pub fn did_from_seed(seed: &str) -> GenesisDID<AccountId> {
+	let key_pair = ed25519::Pair::from_string(&format!("//{}", seed), None)
+		.expect("static values are valid; qed");
+	let did_uri = format!("did:trackback:dev:{}", seed.to_lowercase());
+	let did_document = format!(
+		r#"{{"@context":["https://www.w3.org/ns/did/v1"],"id":"{uri}","authentication":[{{"id":"{uri}#keys-1","type":"Ed25519VerificationKey2018","controller":"{uri}","publicKeyHex":"{key}"}}]}}"#,
+		uri = did_uri,
+		key = HexDisplay::from(&key_pair.public().0),
+	);
+
+	GenesisDID {
+		did_uri: did_uri.into_bytes(),
+		signatures: vec![DIDSignature {
+			public_key: key_pair.public().0.to_vec(),
+			proof: key_pair.sign(did_document.as_bytes()),
+			active: true,
+			created_time_stamp: 0,
+			updated_time_stamp: 0,
+		}],
+		did_document: did_document.into_bytes(),
+		controller: get_account_id_from_seed::<sr25519::Public>(seed),
+		did_ref: None,
+		did_document_metadata: None,
+		did_resolution_metadata: None,
+	}
+}
+
+/// Well-known nodes of the `dev` and `local` chains, the peer IDs of the `--node-key`s of the
+/// permissioned network tutorial, owned by Alice and Bob.
+fn development_nodes() -> Vec<(OpaquePeerId, AccountId)> {
+	vec![
+		(
+			parse_peer_id("12D3KooWBmAwcd4PJNJvfV89HwE48nwkRmAgo8Vy3uQEyNNHBox2")
+				.expect("static values are valid; qed"),
+			get_account_id_from_seed::<sr25519::Public>("Alice"),
+		),
+		(
+			parse_peer_id("12D3KooWQYV9dGMFoRzNStwpXztXaBUjtPqi6aU76ZgUriHhKust")
+				.expect("static values are valid; qed"),
+			get_account_id_from_seed::<sr25519::Public>("Bob"),
+		),
+	]
+}
+
+fn parse_peer_id(peer_id: &str) -> Result<OpaquePeerId, String> {
+	crate::node_auth::parse_peer_id(peer_id).map(OpaquePeerId)
+}
+
+/// DID of a `--did-seed` file, documents and metadata are plain text, keys and proofs are
+/// `0x` prefixed hex and controllers are SS58 addresses
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct SeedDID {
+	did_uri: String,
+	did_document: String,
+	controller: AccountId,
+	#[serde(default)]
+	did_ref: Option<String>,
+	#[serde(default)]
+	did_document_metadata: Option<String>,
+	#[serde(default)]
+	did_resolution_metadata: Option<String>,
+	signatures: Vec<SeedSignature>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct SeedSignature {
+	#[serde(with = "sp_core::bytes")]
+	public_key: Vec<u8>,
+	#[serde(with = "sp_core::bytes")]
+	proof: Vec<u8>,
+}
+
+/// Load the DIDs of a `--did-seed` file, proofs are checked here so a bad file fails before
+/// the genesis is built
+pub fn load_seed_dids(path: &Path) -> Result<Vec<GenesisDID<AccountId>>, String> {
+	let file = std::fs::read(path)
+		.map_err(|e| format!("Error reading DID seed file {}: {}", path.display(), e))?;
+	let seeds: Vec<SeedDID> = serde_json::from_slice(&file)
+		.map_err(|e| format!("Error parsing DID seed file {}: {}", path.display(), e))?;
+
+	genesis_dids(seeds)
+}
+
+fn genesis_dids(seeds: Vec<SeedDID>) -> Result<Vec<GenesisDID<AccountId>>, String> {
+	seeds
+		.into_iter()
+		.map(|seed| {
+			let signatures = seed
+				.signatures
+				.into_iter()
+				.map(|signature| {
+					let proof = ed25519::Signature::try_from(&signature.proof[..])
+						.map_err(|_| format!("Invalid proof length for {}", seed.did_uri))?;
+					if !DIDModule::verify_signature(
+						&signature.public_key,
+						&proof,
+						seed.did_document.as_bytes(),
+					) {
+						return Err(format!("Invalid proof for {}", seed.did_uri))
+					}
+					Ok(DIDSignature {
+						public_key: signature.public_key,
+						proof,
+						active: true,
+						created_time_stamp: 0,
+						updated_time_stamp: 0,
+					})
+				})
+				.collect::<Result<Vec<_>, String>>()?;
+
+			Ok(GenesisDID {
+				did_uri: seed.did_uri.into_bytes(),
+				did_document: seed.did_document.into_bytes(),
+				controller: seed.controller,
+				did_ref: seed.did_ref.map(String::into_bytes),
+				did_document_metadata: seed.did_document_metadata.map(String::into_bytes),
+				did_resolution_metadata: seed.did_resolution_metadata.map(String::into_bytes),
+				signatures,
+			})
+		})
+		.collect()
+}
+
+/// Chain described by a `generate-spec` file, accounts and session keys are SS58 addresses, peer
+/// IDs are base58 and DIDs follow the `--did-seed` format
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ChainDescription {
+	name: String,
+	id: String,
+	#[serde(default)]
+	chain_type: ChainType,
+	#[serde(default)]
+	boot_nodes: Vec<MultiaddrWithPeerId>,
+	#[serde(default)]
+	protocol_id: Option<String>,
+	authorities: Vec<AuthorityDescription>,
+	#[serde(default)]
+	sudo: Option<AccountId>,
+	#[serde(default)]
+	council: Vec<AccountId>,
+	#[serde(default)]
+	endowed_accounts: Vec<AccountId>,
+	#[serde(default)]
+	well_known_nodes: Vec<NodeDescription>,
+	#[serde(default)]
+	dids: Vec<SeedDID>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct AuthorityDescription {
+	account: AccountId,
+	aura: AuraId,
+	grandpa: GrandpaId,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct NodeDescription {
+	peer_id: String,
+	owner: AccountId,
+}
+
+/// Load a chain description, TOML if the file ends with `.toml`, JSON otherwise
+pub fn load_description(path: &Path) -> Result<ChainDescription, String> {
+	let file = std::fs::read_to_string(path)
+		.map_err(|e| format!("Error reading chain description {}: {}", path.display(), e))?;
+
+	match path.extension() {
+		| Some(extension) if extension == "toml" =>
+			toml::from_str(&file).map_err(|e| e.to_string()),
+		| _ => serde_json::from_str(&file).map_err(|e| e.to_string()),
+	}
+	.map_err(|e| format!("Error parsing chain description {}: {}", path.display(), e))
+}
+
+/// Chain spec of a chain description, checked so a bad description fails before the genesis is
+/// built
+pub fn description_config(description: ChainDescription) -> Result<ChainSpec, String> {
+	let wasm_binary = WASM_BINARY.ok_or_else(|| "Wasm binary not available".to_string())?;
+
+	if description.authorities.is_empty() {
+		return Err("The chain needs at least one authority".into())
+	}
+	if description.sudo.is_none() && description.council.is_empty() {
+		return Err("The chain needs a sudo key or council members to be governed".into())
+	}
+	let authorities: Vec<_> = description
+		.authorities
+		.into_iter()
+		.map(|authority| (authority.account, authority.aura, authority.grandpa))
+		.collect();
+	let well_known_nodes = description
+		.well_known_nodes
+		.into_iter()
+		.map(|node| Ok((parse_peer_id(&node.peer_id)?, node.owner)))
+		.collect::<Result<Vec<_>, String>>()?;
+	let dids = genesis_dids(description.dids)?;
+	let (sudo, council, endowed_accounts) =
+		(description.sudo, description.council, description.endowed_accounts);
+
+	Ok(ChainSpec::from_genesis(
+		&description.name,
+		&description.id,
+		description.chain_type,
+		move || {
+			testnet_genesis(
+				wasm_binary,
+				authorities.clone(),
+				sudo.clone(),
+				council.clone(),
+				endowed_accounts.clone(),
+				well_known_nodes.clone(),
+				dids.clone(),
+				true,
+			)
+		},
+		description.boot_nodes,
+		// Telemetry
+		None,
+		description.protocol_id.as_deref(),
+		// Properties
+		None,
+		// Extensions
+		None,
+	))
+}
+
+pub fn development_config(
+	seed_dids: Option<Vec<GenesisDID<AccountId>>>,
+	sudo: bool,
+) -> Result<ChainSpec, String> {
 	let wasm_binary =
 		WASM_BINARY.ok_or_else(|| "Development wasm binary not available".to_string())?;
 
@@ -52,7 +300,9 @@ pub fn development_config() -> Result<ChainSpec, String> {
 				// Initial PoA authorities
 				vec![authority_keys_from_seed("Alice")],
 				// Sudo account
-				get_account_id_from_seed::<sr25519::Public>("Alice"),
+				Some(get_account_id_from_seed::<sr25519::Public>("Alice")).filter(|_| sudo),
+				// Council members
+				vec![get_account_id_from_seed::<sr25519::Public>("Alice")],
 				// Pre-funded accounts
 				vec![
 					get_account_id_from_seed::<sr25519::Public>("Alice"),
@@ -60,6 +310,12 @@ pub fn development_config() -> Result<ChainSpec, String> {
 					get_account_id_from_seed::<sr25519::Public>("Alice//stash"),
 					get_account_id_from_seed::<sr25519::Public>("Bob//stash"),
 				],
+				// Well-known nodes
+				development_nodes(),
+				// Pre-seeded DIDs
+				seed_dids
+					.clone()
+					.unwrap_or_else(|| vec![did_from_seed("Alice"), did_from_seed("Bob")]),
 				true,
 			)
 		},
@@ -76,11 +332,17 @@ pub fn development_config() -> Result<ChainSpec, String> {
 	))
 }
 
-pub fn test_net_config() -> ChainSpec {
-	ChainSpec::from_json_bytes(&include_bytes!("../chain_specs/staging-net.json")[..]).unwrap()
+/// The `test-net` chain, loaded from the committed spec so that its genesis does not change with
+/// the runtime. Regenerate `chain_specs/staging-net.json` from `chain_specs/staging-net.toml` only
+/// when relaunching the network.
+pub fn test_net_config() -> Result<ChainSpec, String> {
+	ChainSpec::from_json_bytes(&include_bytes!("../chain_specs/staging-net.json")[..])
 }
 
-pub fn local_testnet_config() -> Result<ChainSpec, String> {
+pub fn local_testnet_config(
+	seed_dids: Option<Vec<GenesisDID<AccountId>>>,
+	sudo: bool,
+) -> Result<ChainSpec, String> {
 	let wasm_binary =
 		WASM_BINARY.ok_or_else(|| "Development wasm binary not available".to_string())?;
 
@@ -96,7 +358,13 @@ pub fn local_testnet_config() -> Result<ChainSpec, String> {
 				// Initial PoA authorities
 				vec![authority_keys_from_seed("Alice"), authority_keys_from_seed("Bob")],
 				// Sudo account
-				get_account_id_from_seed::<sr25519::Public>("Alice"),
+				Some(get_account_id_from_seed::<sr25519::Public>("Alice")).filter(|_| sudo),
+				// Council members
+				vec![
+					get_account_id_from_seed::<sr25519::Public>("Alice"),
+					get_account_id_from_seed::<sr25519::Public>("Bob"),
+					get_account_id_from_seed::<sr25519::Public>("Charlie"),
+				],
 				// Pre-funded accounts
 				vec![
 					get_account_id_from_seed::<sr25519::Public>("Alice"),
@@ -112,6 +380,19 @@ pub fn local_testnet_config() -> Result<ChainSpec, String> {
 					get_account_id_from_seed::<sr25519::Public>("Eve//stash"),
 					get_account_id_from_seed::<sr25519::Public>("Ferdie//stash"),
 				],
+				// Well-known nodes
+				development_nodes(),
+				// Pre-seeded DIDs
+				seed_dids.clone().unwrap_or_else(|| {
+					vec![
+						did_from_seed("Alice"),
+						did_from_seed("Bob"),
+						did_from_seed("Charlie"),
+						did_from_seed("Dave"),
+						did_from_seed("Eve"),
+						did_from_seed("Ferdie"),
+					]
+				}),
 				true,
 			)
 		},
@@ -131,9 +412,12 @@ pub fn local_testnet_config() -> Result<ChainSpec, String> {
 /// Configure initial storage state for FRAME modules.
 fn testnet_genesis(
 	wasm_binary: &[u8],
-	initial_authorities: Vec<(AuraId, GrandpaId)>,
-	root_key: AccountId,
+	initial_authorities: Vec<(AccountId, AuraId, GrandpaId)>,
+	root_key: Option<AccountId>,
+	council_members: Vec<AccountId>,
 	endowed_accounts: Vec<AccountId>,
+	well_known_nodes: Vec<(OpaquePeerId, AccountId)>,
+	dids: Vec<GenesisDID<AccountId>>,
 	_enable_println: bool,
 ) -> GenesisConfig {
 	GenesisConfig {
@@ -146,37 +430,84 @@ fn testnet_genesis(
 			// Configure endowed accounts with initial balance of 1 << 60.
 			balances: endowed_accounts.iter().cloned().map(|k| (k, 1 << 60)).collect(),
 		}),
-		pallet_aura: Some(AuraConfig {
-			authorities: initial_authorities.iter().map(|x| (x.0.clone())).collect(),
+		// Aura and Grandpa authorities are set by the genesis session of the validator set
+		pallet_aura: Some(AuraConfig { authorities: vec![] }),
+		pallet_grandpa: Some(GrandpaConfig { authorities: vec![] }),
+		pallet_validator_set: Some(ValidatorSetConfig {
+			validators: initial_authorities.iter().map(|x| x.0.clone()).collect(),
 		}),
-		pallet_grandpa: Some(GrandpaConfig {
-			authorities: initial_authorities.iter().map(|x| (x.1.clone(), 1)).collect(),
+		pallet_session: Some(SessionConfig {
+			keys: initial_authorities
+				.iter()
+				.map(|x| (x.0.clone(), x.0.clone(), session_keys(x.1.clone(), x.2.clone())))
+				.collect(),
 		}),
-		pallet_sudo: Some(SudoConfig {
+		// Without a sudo key, upgrades and admin calls go through the council and referenda
+		pallet_sudo: root_key.map(|key| SudoConfig {
 			// Assign network admin rights.
-			key: root_key,
+			key,
 		}),
-		// TODO: this configuration is only for MVP chain
-		// TODO: Change this for live net
-		pallet_node_authorization: Some(NodeAuthorizationConfig {
-			nodes: vec![
-				(
-					OpaquePeerId(
-						bs58::decode("12D3KooWBmAwcd4PJNJvfV89HwE48nwkRmAgo8Vy3uQEyNNHBox2")
-							.into_vec()
-							.unwrap(),
-					),
-					endowed_accounts[0].clone(),
-				),
-				(
-					OpaquePeerId(
-						bs58::decode("12D3KooWQYV9dGMFoRzNStwpXztXaBUjtPqi6aU76ZgUriHhKust")
-							.into_vec()
-							.unwrap(),
-					),
-					endowed_accounts[1].clone(),
-				),
-			],
+		pallet_collective_Instance1: Some(CouncilConfig {
+			members: council_members,
+			phantom: Default::default(),
 		}),
+		pallet_democracy: Some(DemocracyConfig::default()),
+		pallet_node_authorization: Some(NodeAuthorizationConfig { nodes: well_known_nodes }),
+		pallet_dids: Some(DIDModuleConfig { dids, vcs: vec![] }),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sc_service::ChainSpec as _;
+	use sp_runtime::BuildStorage;
+
+	fn description(toml: &str) -> ChainDescription {
+		toml::from_str(toml).unwrap()
+	}
+
+	#[test]
+	fn generate_the_staging_net_spec() {
+		let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("chain_specs/staging-net.toml");
+		let spec = description_config(load_description(&path).unwrap()).unwrap();
+
+		assert!(spec.build_storage().is_ok());
+		assert!(spec.as_json(true).is_ok());
+	}
+
+	#[test]
+	fn load_the_committed_test_net_spec() {
+		let spec = test_net_config().unwrap();
+
+		assert_eq!((spec.name(), spec.id()), ("Local Testnet", "local_testnet"));
+		assert!(spec.build_storage().is_ok());
+	}
+
+	#[test]
+	fn reject_invalid_descriptions() {
+		let authority = r#"
+			[[authorities]]
+			account = "5FA9nQDVg267DEd8m1ZypXLBnvN7SFxYwV7ndqSYGiN9TTpu"
+			aura = "5FA9nQDVg267DEd8m1ZypXLBnvN7SFxYwV7ndqSYGiN9TTpu"
+			grandpa = "5CYiXPJu5io6KQs1UxVnsKsGLtzw6ENm8MEqVpePwe2TqR92"
+		"#;
+
+		let ungoverned = description(&format!("name = \"Test\"\nid = \"test\"\n{}", authority));
+		assert!(description_config(ungoverned).is_err());
+
+		let bad_peer_id = description(&format!(
+			r#"
+			name = "Test"
+			id = "test"
+			sudo = "5FA9nQDVg267DEd8m1ZypXLBnvN7SFxYwV7ndqSYGiN9TTpu"
+			{}
+			[[wellKnownNodes]]
+			peerId = "0OIl"
+			owner = "5FA9nQDVg267DEd8m1ZypXLBnvN7SFxYwV7ndqSYGiN9TTpu"
+			"#,
+			authority
+		));
+		assert!(description_config(bad_peer_id).is_err());
 	}
 }
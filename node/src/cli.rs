@@ -1,4 +1,5 @@
 use sc_cli::RunCmd;
+use std::path::PathBuf;
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
@@ -8,6 +9,10 @@ pub struct Cli {
 
 	#[structopt(flatten)]
 	pub run: RunCmd,
+
+	/// IPFS gateway the `did_resolve` RPC fetches DID documents from, e.g. http://127.0.0.1:8080
+	#[structopt(long = "ipfs-gateway")]
+	pub ipfs_gateway: Option<String>,
 }
 
 #[derive(Debug, StructOpt)]
@@ -15,7 +20,10 @@ pub enum Subcommand {
 	/// Key management cli utilities
 	Key(sc_cli::KeySubcommand),
 	/// Build a chain specification.
-	BuildSpec(sc_cli::BuildSpecCmd),
+	BuildSpec(BuildSpecCmd),
+
+	/// Generate a chain specification from a TOML or JSON description of the chain.
+	GenerateSpec(GenerateSpecCmd),
 
 	/// Validate blocks.
 	CheckBlock(sc_cli::CheckBlockCmd),
@@ -35,7 +43,42 @@ pub enum Subcommand {
 	/// Revert the chain to a previous state.
 	Revert(sc_cli::RevertCmd),
 
+	/// Manage the well-known nodes and connections of the permissioned network.
+	NodeAuth(crate::node_auth::NodeAuthCmd),
+
 	/// The custom benchmark subcommmand benchmarking runtime pallets.
 	#[structopt(name = "benchmark", about = "Benchmark runtime pallets.")]
 	Benchmark(frame_benchmarking_cli::BenchmarkCmd),
 }
+
+#[derive(Debug, StructOpt)]
+pub struct BuildSpecCmd {
+	#[structopt(flatten)]
+	pub base: sc_cli::BuildSpecCmd,
+
+	/// JSON file of the DIDs seeded at genesis of the `dev` and `local` chains, replacing the
+	/// development DIDs
+	#[structopt(long = "did-seed", parse(from_os_str))]
+	pub did_seed: Option<PathBuf>,
+
+	/// Leave the sudo key out of the `dev` and `local` chains, so the council and referenda are
+	/// the only admin path
+	#[structopt(long = "no-sudo")]
+	pub no_sudo: bool,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct GenerateSpecCmd {
+	/// TOML or JSON file describing the authorities, sudo key, council, endowed accounts,
+	/// well-known nodes and DIDs of the chain
+	#[structopt(parse(from_os_str))]
+	pub description: PathBuf,
+
+	/// Output the chain spec with the genesis storage encoded
+	#[structopt(long = "raw")]
+	pub raw: bool,
+
+	/// File the chain spec is written to, instead of the standard output
+	#[structopt(long = "output", short = "o", parse(from_os_str))]
+	pub output: Option<PathBuf>,
+}
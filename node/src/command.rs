@@ -50,10 +50,20 @@ impl SubstrateCli for Cli {
 	}
 
 	fn load_spec(&self, id: &str) -> Result<Box<dyn sc_service::ChainSpec>, String> {
+		let (seed_dids, sudo) = match &self.subcommand {
+			| Some(Subcommand::BuildSpec(cmd)) =>
+				(cmd.did_seed.as_deref().map(chain_spec::load_seed_dids).transpose()?, !cmd.no_sudo),
+			| _ => (None, true),
+		};
+
 		Ok(match id {
-			| "dev" => Box::new(chain_spec::development_config()?),
-			| "" | "local" => Box::new(chain_spec::local_testnet_config()?),
-			| "test-net" => Box::new(chain_spec::test_net_config()),
+			| "dev" => Box::new(chain_spec::development_config(seed_dids, sudo)?),
+			| "" | "local" => Box::new(chain_spec::local_testnet_config(seed_dids, sudo)?),
+			| _ if seed_dids.is_some() =>
+				return Err("`--did-seed` only applies to the `dev` and `local` chains".into()),
+			| _ if !sudo =>
+				return Err("`--no-sudo` only applies to the `dev` and `local` chains".into()),
+			| "test-net" => Box::new(chain_spec::test_net_config()?),
 			| path =>
 				Box::new(chain_spec::ChainSpec::from_json_file(std::path::PathBuf::from(path))?),
 		})
@@ -71,8 +81,20 @@ pub fn run() -> sc_cli::Result<()> {
 	match &cli.subcommand {
 		| Some(Subcommand::Key(cmd)) => cmd.run(&cli),
 		| Some(Subcommand::BuildSpec(cmd)) => {
-			let runner = cli.create_runner(cmd)?;
-			runner.sync_run(|config| cmd.run(config.chain_spec, config.network))
+			let runner = cli.create_runner(&cmd.base)?;
+			runner.sync_run(|config| cmd.base.run(config.chain_spec, config.network))
+		},
+		| Some(Subcommand::GenerateSpec(cmd)) => {
+			let spec = chain_spec::load_description(&cmd.description)
+				.and_then(chain_spec::description_config)
+				.and_then(|spec| spec.as_json(cmd.raw))
+				.map_err(sc_cli::Error::Input)?;
+
+			match &cmd.output {
+				| Some(path) => std::fs::write(path, spec)?,
+				| None => println!("{}", spec),
+			}
+			Ok(())
 		},
 		| Some(Subcommand::CheckBlock(cmd)) => {
 			let runner = cli.create_runner(cmd)?;
@@ -116,6 +138,7 @@ pub fn run() -> sc_cli::Result<()> {
 				Ok((cmd.run(client, backend), task_manager))
 			})
 		},
+		| Some(Subcommand::NodeAuth(cmd)) => cmd.run(),
 		| Some(Subcommand::Benchmark(cmd)) =>
 			if cfg!(feature = "runtime-benchmarks") {
 				let runner = cli.create_runner(cmd)?;
@@ -128,10 +151,11 @@ pub fn run() -> sc_cli::Result<()> {
 			},
 		| None => {
 			let runner = cli.create_runner(&cli.run)?;
+			let ipfs_gateway = cli.ipfs_gateway.clone();
 			runner.run_node_until_exit(|config| async move {
 				match config.role {
 					| Role::Light => service::new_light(config),
-					| _ => service::new_full(config),
+					| _ => service::new_full(config, ipfs_gateway),
 				}
 				.map_err(sc_cli::Error::Service)
 			})
@@ -0,0 +1,569 @@
+//! Remote keystore.
+//! Keeps validator keys in a separate signer process, e.g. an HSM front, reached with
+//! `--keystore-uri http://127.0.0.1:<port>`. The node never sees private keys, it sends JSON-RPC
+//! 2.0 requests over HTTP to the signer:
+//! * `keystore_keys` `{keyType}` -> `[{cryptoType, public}]`
+//! * `keystore_generate` `{keyType, cryptoType}` -> `public`
+//! * `keystore_sign` `{keyType, cryptoType, public, message}` -> `signature`
+//!
+//! Key and crypto types are their four character ids, e.g. `aura` and `sr25`, keys, messages and
+//! signatures are `0x` prefixed hex. VRF signing is not supported, Aura and Grandpa do not use it.
+//! Secret URIs are never sent over plain HTTP, so `author_insertKey` and keys generated from a
+//! seed, e.g. the dev keys of `--alice`, are rejected, keys are inserted into the signer directly.
+//! Async callers wait for the signer on a small pool of threads.
+//!
+//! Requests are not authenticated, anyone reaching the signer can sign with the validator's keys.
+//! It must only listen on the loopback interface, or a private interface of the validator's host.
+
+use async_trait::async_trait;
+use codec::DecodeAll;
+use futures::{executor::ThreadPool, task::SpawnExt};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sp_core::{
+	bytes::{from_hex, to_hex},
+	crypto::{CryptoTypeId, CryptoTypePublicPair, KeyTypeId},
+	ecdsa, ed25519, sr25519,
+};
+use sp_keystore::{
+	vrf::{VRFSignature, VRFTranscriptData},
+	CryptoStore, Error, SyncCryptoStore,
+};
+use std::{
+	convert::TryFrom,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc,
+	},
+	time::Duration,
+};
+
+/// Time allowed for a request to the signer
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Number of threads async callers wait for the signer on, further requests queue
+const REQUEST_THREADS: usize = 2;
+
+/// Keystore whose keys live in a remote signer
+#[derive(Clone)]
+pub struct RemoteKeystore {
+	url: String,
+	agent: ureq::Agent,
+	next_id: Arc<AtomicU64>,
+	pool: ThreadPool,
+}
+
+#[derive(Deserialize)]
+struct Response {
+	#[serde(default)]
+	result: Value,
+	error: Option<ResponseError>,
+}
+
+#[derive(Deserialize)]
+struct ResponseError {
+	message: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RemoteKey {
+	crypto_type: String,
+	public: String,
+}
+
+impl RemoteKeystore {
+	/// Opens the keystore of the signer listening on `url`, only `http://` is supported
+	pub fn open(url: &str) -> Result<Self, String> {
+		if !url.starts_with("http://") {
+			return Err(format!("Unsupported remote keystore URI {}, use http://", url))
+		}
+
+		let pool = ThreadPool::builder()
+			.pool_size(REQUEST_THREADS)
+			.name_prefix("remote-keystore-")
+			.create()
+			.map_err(|e| format!("Unable to start the remote keystore threads: {}", e))?;
+
+		Ok(Self {
+			url: url.to_string(),
+			agent: ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build(),
+			next_id: Arc::new(AtomicU64::new(0)),
+			pool,
+		})
+	}
+
+	/// Runs requests to the signer on the keystore's threads, so async callers do not block the
+	/// executor while the signer answers, and a stalled signer does not pile up threads
+	async fn spawn_blocking<R, F>(&self, f: F) -> R
+	where
+		R: Send + 'static,
+		F: FnOnce(&Self) -> R + Send + 'static,
+	{
+		let keystore = self.clone();
+		self.pool
+			.spawn_with_handle(async move { f(&keystore) })
+			.expect("The keystore's threads run as long as the keystore; qed")
+			.await
+	}
+
+	/// Sends a request to the signer, returning its result
+	fn request(&self, method: &str, params: Value) -> Result<Value, Error> {
+		let body = json!({
+			"jsonrpc": "2.0",
+			"id": self.next_id.fetch_add(1, Ordering::Relaxed),
+			"method": method,
+			"params": params,
+		});
+
+		let response = self
+			.agent
+			.post(&self.url)
+			.set("Content-Type", "application/json")
+			.send_string(&body.to_string())
+			.map_err(|_| Error::Unavailable)?
+			.into_string()
+			.map_err(|e| Error::Other(e.to_string()))?;
+		let response: Response =
+			serde_json::from_str(&response).map_err(|e| Error::Other(e.to_string()))?;
+
+		match response.error {
+			| Some(error) => Err(Error::Other(error.message)),
+			| None => Ok(response.result),
+		}
+	}
+
+	/// Keys of a key type held by the signer
+	fn remote_keys(&self, id: KeyTypeId) -> Result<Vec<CryptoTypePublicPair>, Error> {
+		let keys: Vec<RemoteKey> = serde_json::from_value(
+			self.request("keystore_keys", json!({ "keyType": key_type(id) }))?,
+		)
+		.map_err(|e| Error::Other(e.to_string()))?;
+
+		keys.into_iter()
+			.map(|key| {
+				Ok(CryptoTypePublicPair(
+					crypto_type_id(&key.crypto_type)?,
+					from_hex(&key.public).map_err(|e| Error::Other(e.to_string()))?,
+				))
+			})
+			.collect()
+	}
+
+	/// Public keys of a key type and crypto held by the signer, unavailable signers hold none
+	fn public_keys<P: DecodeAll>(&self, id: KeyTypeId, crypto: CryptoTypeId) -> Vec<P> {
+		self.remote_keys(id)
+			.unwrap_or_default()
+			.into_iter()
+			.filter(|key| key.0 == crypto)
+			.filter_map(|key| P::decode_all(&key.1).ok())
+			.collect()
+	}
+
+	/// Asks the signer to generate a key, seeds are secret URIs and are not sent
+	fn generate<P: DecodeAll>(
+		&self,
+		id: KeyTypeId,
+		crypto: CryptoTypeId,
+		seed: Option<&str>,
+	) -> Result<P, Error> {
+		if seed.is_some() {
+			return Err(Error::ValidationError(
+				"Seeds are not sent to the remote signer over plain HTTP".into(),
+			))
+		}
+
+		let public = self.request(
+			"keystore_generate",
+			json!({ "keyType": key_type(id), "cryptoType": crypto_type(crypto) }),
+		)?;
+
+		decode_hex(&public).and_then(|public| {
+			P::decode_all(&public)
+				.map_err(|_| Error::ValidationError("Invalid public key from signer".into()))
+		})
+	}
+}
+
+impl SyncCryptoStore for RemoteKeystore {
+	fn sr25519_public_keys(&self, id: KeyTypeId) -> Vec<sr25519::Public> {
+		self.public_keys(id, sr25519::CRYPTO_ID)
+	}
+
+	fn sr25519_generate_new(
+		&self,
+		id: KeyTypeId,
+		seed: Option<&str>,
+	) -> Result<sr25519::Public, Error> {
+		self.generate(id, sr25519::CRYPTO_ID, seed)
+	}
+
+	fn ed25519_public_keys(&self, id: KeyTypeId) -> Vec<ed25519::Public> {
+		self.public_keys(id, ed25519::CRYPTO_ID)
+	}
+
+	fn ed25519_generate_new(
+		&self,
+		id: KeyTypeId,
+		seed: Option<&str>,
+	) -> Result<ed25519::Public, Error> {
+		self.generate(id, ed25519::CRYPTO_ID, seed)
+	}
+
+	fn ecdsa_public_keys(&self, id: KeyTypeId) -> Vec<ecdsa::Public> {
+		self.public_keys(id, ecdsa::CRYPTO_ID)
+	}
+
+	fn ecdsa_generate_new(
+		&self,
+		id: KeyTypeId,
+		seed: Option<&str>,
+	) -> Result<ecdsa::Public, Error> {
+		self.generate(id, ecdsa::CRYPTO_ID, seed)
+	}
+
+	/// Secret URIs are not sent to the signer over plain HTTP
+	fn insert_unknown(&self, _key_type: KeyTypeId, _suri: &str, _public: &[u8]) -> Result<(), ()> {
+		Err(())
+	}
+
+	fn supported_keys(
+		&self,
+		id: KeyTypeId,
+		keys: Vec<CryptoTypePublicPair>,
+	) -> Result<Vec<CryptoTypePublicPair>, Error> {
+		let remote_keys = self.remote_keys(id)?;
+
+		Ok(keys.into_iter().filter(|key| remote_keys.contains(key)).collect())
+	}
+
+	fn keys(&self, id: KeyTypeId) -> Result<Vec<CryptoTypePublicPair>, Error> {
+		self.remote_keys(id)
+	}
+
+	fn has_keys(&self, public_keys: &[(Vec<u8>, KeyTypeId)]) -> bool {
+		public_keys.iter().all(|(public, id)| {
+			self.remote_keys(*id)
+				.map(|keys| keys.iter().any(|key| &key.1 == public))
+				.unwrap_or(false)
+		})
+	}
+
+	fn sign_with(
+		&self,
+		id: KeyTypeId,
+		key: &CryptoTypePublicPair,
+		msg: &[u8],
+	) -> Result<Vec<u8>, Error> {
+		let signature = self.request(
+			"keystore_sign",
+			json!({
+				"keyType": key_type(id),
+				"cryptoType": crypto_type(key.0),
+				"public": to_hex(&key.1, false),
+				"message": to_hex(msg, false),
+			}),
+		)?;
+
+		decode_hex(&signature)
+	}
+
+	fn sr25519_vrf_sign(
+		&self,
+		_key_type: KeyTypeId,
+		_public: &sr25519::Public,
+		_transcript_data: VRFTranscriptData,
+	) -> Result<VRFSignature, Error> {
+		Err(Error::Other("VRF signing is not supported by the remote keystore".into()))
+	}
+}
+
+#[async_trait]
+impl CryptoStore for RemoteKeystore {
+	async fn sr25519_public_keys(&self, id: KeyTypeId) -> Vec<sr25519::Public> {
+		self.spawn_blocking(move |keystore| SyncCryptoStore::sr25519_public_keys(keystore, id))
+			.await
+	}
+
+	async fn sr25519_generate_new(
+		&self,
+		id: KeyTypeId,
+		seed: Option<&str>,
+	) -> Result<sr25519::Public, Error> {
+		let seed = seed.map(str::to_owned);
+		self.spawn_blocking(move |keystore| {
+			SyncCryptoStore::sr25519_generate_new(keystore, id, seed.as_deref())
+		})
+		.await
+	}
+
+	async fn ed25519_public_keys(&self, id: KeyTypeId) -> Vec<ed25519::Public> {
+		self.spawn_blocking(move |keystore| SyncCryptoStore::ed25519_public_keys(keystore, id))
+			.await
+	}
+
+	async fn ed25519_generate_new(
+		&self,
+		id: KeyTypeId,
+		seed: Option<&str>,
+	) -> Result<ed25519::Public, Error> {
+		let seed = seed.map(str::to_owned);
+		self.spawn_blocking(move |keystore| {
+			SyncCryptoStore::ed25519_generate_new(keystore, id, seed.as_deref())
+		})
+		.await
+	}
+
+	async fn ecdsa_public_keys(&self, id: KeyTypeId) -> Vec<ecdsa::Public> {
+		self.spawn_blocking(move |keystore| SyncCryptoStore::ecdsa_public_keys(keystore, id))
+			.await
+	}
+
+	async fn ecdsa_generate_new(
+		&self,
+		id: KeyTypeId,
+		seed: Option<&str>,
+	) -> Result<ecdsa::Public, Error> {
+		let seed = seed.map(str::to_owned);
+		self.spawn_blocking(move |keystore| {
+			SyncCryptoStore::ecdsa_generate_new(keystore, id, seed.as_deref())
+		})
+		.await
+	}
+
+	async fn insert_unknown(&self, id: KeyTypeId, suri: &str, public: &[u8]) -> Result<(), ()> {
+		SyncCryptoStore::insert_unknown(self, id, suri, public)
+	}
+
+	async fn supported_keys(
+		&self,
+		id: KeyTypeId,
+		keys: Vec<CryptoTypePublicPair>,
+	) -> Result<Vec<CryptoTypePublicPair>, Error> {
+		self.spawn_blocking(move |keystore| SyncCryptoStore::supported_keys(keystore, id, keys))
+			.await
+	}
+
+	async fn keys(&self, id: KeyTypeId) -> Result<Vec<CryptoTypePublicPair>, Error> {
+		self.spawn_blocking(move |keystore| SyncCryptoStore::keys(keystore, id)).await
+	}
+
+	async fn has_keys(&self, public_keys: &[(Vec<u8>, KeyTypeId)]) -> bool {
+		let public_keys = public_keys.to_vec();
+		self.spawn_blocking(move |keystore| SyncCryptoStore::has_keys(keystore, &public_keys))
+			.await
+	}
+
+	async fn sign_with(
+		&self,
+		id: KeyTypeId,
+		key: &CryptoTypePublicPair,
+		msg: &[u8],
+	) -> Result<Vec<u8>, Error> {
+		let (key, msg) = (key.clone(), msg.to_vec());
+		self.spawn_blocking(move |keystore| SyncCryptoStore::sign_with(keystore, id, &key, &msg))
+			.await
+	}
+
+	async fn sr25519_vrf_sign(
+		&self,
+		key_type: KeyTypeId,
+		public: &sr25519::Public,
+		transcript_data: VRFTranscriptData,
+	) -> Result<VRFSignature, Error> {
+		SyncCryptoStore::sr25519_vrf_sign(self, key_type, public, transcript_data)
+	}
+}
+
+/// Four character id of a key type, e.g. `aura`
+fn key_type(id: KeyTypeId) -> String {
+	String::from_utf8_lossy(&id.0).into_owned()
+}
+
+/// Four character id of a crypto, e.g. `sr25`
+fn crypto_type(id: CryptoTypeId) -> String {
+	String::from_utf8_lossy(&id.0).into_owned()
+}
+
+fn crypto_type_id(id: &str) -> Result<CryptoTypeId, Error> {
+	<[u8; 4]>::try_from(id.as_bytes())
+		.map(CryptoTypeId)
+		.map_err(|_| Error::ValidationError(format!("Invalid crypto type {}", id)))
+}
+
+fn decode_hex(value: &Value) -> Result<Vec<u8>, Error> {
+	value
+		.as_str()
+		.ok_or_else(|| Error::ValidationError("Expected hex from signer".into()))
+		.and_then(|hex| from_hex(hex).map_err(|e| Error::ValidationError(e.to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sc_keystore::LocalKeystore;
+	use sp_core::{
+		crypto::Pair,
+		testing::{ED25519, SR25519},
+		Public,
+	};
+	use std::{
+		io::{BufRead, BufReader, Read, Write},
+		net::TcpListener,
+		sync::Arc,
+		thread,
+	};
+
+	/// Stand-in signer serving the keystore protocol from an in-memory keystore
+	fn signer() -> (String, Arc<LocalKeystore>) {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let url = format!("http://{}", listener.local_addr().unwrap());
+		let keystore = Arc::new(LocalKeystore::in_memory());
+
+		let local = keystore.clone();
+		thread::spawn(move || {
+			for stream in listener.incoming() {
+				let mut reader = BufReader::new(stream.unwrap());
+				let mut content_length = 0;
+				loop {
+					let mut line = String::new();
+					reader.read_line(&mut line).unwrap();
+					if line == "\r\n" {
+						break
+					}
+					if let Some(length) = line.to_lowercase().strip_prefix("content-length:") {
+						content_length = length.trim().parse().unwrap();
+					}
+				}
+				let mut body = vec![0; content_length];
+				reader.read_exact(&mut body).unwrap();
+
+				let response = serve(&local, serde_json::from_slice(&body).unwrap()).to_string();
+				write!(
+					reader.get_mut(),
+					"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+					response.len(),
+					response
+				)
+				.unwrap();
+			}
+		});
+
+		(url, keystore)
+	}
+
+	fn serve(keystore: &LocalKeystore, request: Value) -> Value {
+		let params = &request["params"];
+		let id = KeyTypeId::try_from(params["keyType"].as_str().unwrap()).unwrap();
+		let public = || from_hex(params["public"].as_str().unwrap()).unwrap();
+
+		let result = match request["method"].as_str().unwrap() {
+			| "keystore_keys" => Value::Array(
+				SyncCryptoStore::keys(keystore, id)
+					.unwrap()
+					.into_iter()
+					.map(
+						|key| json!({ "cryptoType": crypto_type(key.0), "public": to_hex(&key.1, false) }),
+					)
+					.collect(),
+			),
+			| "keystore_generate" => {
+				let public = match params["cryptoType"].as_str().unwrap() {
+					| "ed25" => SyncCryptoStore::ed25519_generate_new(keystore, id, None)
+						.unwrap()
+						.to_raw_vec(),
+					| "sr25" => SyncCryptoStore::sr25519_generate_new(keystore, id, None)
+						.unwrap()
+						.to_raw_vec(),
+					| crypto => panic!("Unexpected crypto type {}", crypto),
+				};
+				json!(to_hex(&public, false))
+			},
+			| "keystore_sign" => {
+				let key = CryptoTypePublicPair(
+					crypto_type_id(params["cryptoType"].as_str().unwrap()).unwrap(),
+					public(),
+				);
+				let message = from_hex(params["message"].as_str().unwrap()).unwrap();
+				match SyncCryptoStore::sign_with(keystore, id, &key, &message) {
+					| Ok(signature) => json!(to_hex(&signature, false)),
+					| Err(e) =>
+						return json!({ "jsonrpc": "2.0", "id": request["id"], "error": { "code": 1, "message": e.to_string() } }),
+				}
+			},
+			| method => panic!("Unexpected method {}", method),
+		};
+
+		json!({ "jsonrpc": "2.0", "id": request["id"], "result": result })
+	}
+
+	#[test]
+	fn sign_with_keys_of_a_remote_signer() {
+		let (url, _signer) = signer();
+		let keystore = RemoteKeystore::open(&url).unwrap();
+
+		let public = SyncCryptoStore::ed25519_generate_new(&keystore, ED25519, None).unwrap();
+		assert_eq!(SyncCryptoStore::ed25519_public_keys(&keystore, ED25519), vec![public]);
+		assert!(SyncCryptoStore::sr25519_public_keys(&keystore, ED25519).is_empty());
+		assert!(SyncCryptoStore::has_keys(&keystore, &[(public.to_raw_vec(), ED25519)]));
+
+		let key = CryptoTypePublicPair(ed25519::CRYPTO_ID, public.to_raw_vec());
+		let signature = SyncCryptoStore::sign_with(&keystore, ED25519, &key, b"block").unwrap();
+		let signature = ed25519::Signature::try_from(&signature[..]).unwrap();
+		assert!(ed25519::Pair::verify(&signature, b"block", &public));
+	}
+
+	#[test]
+	fn sign_with_sr25519_keys_without_blocking_async_callers() {
+		let (url, _signer) = signer();
+		let keystore = RemoteKeystore::open(&url).unwrap();
+
+		futures::executor::block_on(async {
+			let public = CryptoStore::sr25519_generate_new(&keystore, SR25519, None).await.unwrap();
+			assert_eq!(CryptoStore::sr25519_public_keys(&keystore, SR25519).await, vec![public]);
+			assert!(CryptoStore::ed25519_public_keys(&keystore, SR25519).await.is_empty());
+
+			let key = CryptoTypePublicPair(sr25519::CRYPTO_ID, public.to_raw_vec());
+			let signature =
+				CryptoStore::sign_with(&keystore, SR25519, &key, b"block").await.unwrap();
+			let signature = sr25519::Signature::try_from(&signature[..]).unwrap();
+			assert!(sr25519::Pair::verify(&signature, b"block", &public));
+		});
+	}
+
+	#[test]
+	fn never_send_secret_uris_to_the_signer() {
+		let (url, _signer) = signer();
+		let keystore = RemoteKeystore::open(&url).unwrap();
+		let public = sr25519::Pair::from_string("//Alice", None).unwrap().public();
+
+		// The stand-in signer panics on unexpected methods
+		assert_eq!(
+			SyncCryptoStore::insert_unknown(&keystore, SR25519, "//Alice", public.as_ref()),
+			Err(())
+		);
+		assert!(matches!(
+			SyncCryptoStore::sr25519_generate_new(&keystore, SR25519, Some("//Alice")),
+			Err(Error::ValidationError(_))
+		));
+		assert!(SyncCryptoStore::sr25519_public_keys(&keystore, SR25519).is_empty());
+	}
+
+	#[test]
+	fn surface_signer_errors() {
+		let (url, _signer) = signer();
+		let keystore = RemoteKeystore::open(&url).unwrap();
+
+		let unknown = CryptoTypePublicPair(ed25519::CRYPTO_ID, vec![0; 32]);
+		assert!(matches!(
+			SyncCryptoStore::sign_with(&keystore, ED25519, &unknown, b"block"),
+			Err(Error::Other(_))
+		));
+		assert!(RemoteKeystore::open("unix:///tmp/signer.sock").is_err());
+
+		// Nothing listens on the discard port
+		let keystore = RemoteKeystore::open("http://127.0.0.1:9").unwrap();
+		assert!(SyncCryptoStore::ed25519_public_keys(&keystore, ED25519).is_empty());
+		assert!(matches!(SyncCryptoStore::keys(&keystore, ED25519), Err(Error::Unavailable)));
+	}
+}
@@ -1,3 +1,6 @@
 pub mod chain_spec;
+pub mod keystore;
+pub mod monitor;
+pub mod node_auth;
 pub mod rpc;
 pub mod service;
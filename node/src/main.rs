@@ -2,10 +2,13 @@
 #![warn(missing_docs)]
 
 mod chain_spec;
+mod keystore;
 #[macro_use]
 mod service;
 mod cli;
 mod command;
+mod monitor;
+mod node_auth;
 mod rpc;
 
 fn main() -> sc_cli::Result<()> {
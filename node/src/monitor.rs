@@ -0,0 +1,394 @@
+//! Aura authority monitor.
+//! Follows imported blocks and counts, per Aura authority, the best blocks it authored, the slots
+//! it missed and its equivocations, i.e. two blocks authored for the same slot. Counts are kept
+//! in memory since the node started and exposed as Prometheus metrics and through the
+//! `auraMonitor_authorities` RPC. A reorg may count the missed slots of both branches.
+
+use futures::StreamExt;
+use jsonrpc_core::Result as RpcResult;
+use jsonrpc_derive::rpc;
+use parking_lot::RwLock;
+use sc_client_api::BlockchainEvents;
+use serde::{Deserialize, Serialize};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_consensus_aura::{
+	digests::CompatibleDigestItem,
+	sr25519::{AuthorityId as AuraId, AuthoritySignature as AuraSignature},
+	AuraApi,
+};
+use sp_core::{crypto::Public, hexdisplay::HexDisplay, Bytes};
+use sp_runtime::{
+	generic::BlockId,
+	traits::{Block as BlockT, Header as HeaderT},
+};
+use std::{
+	collections::{btree_map::Entry, BTreeMap, HashMap},
+	future::Future,
+	sync::Arc,
+};
+use substrate_prometheus_endpoint::{register, CounterVec, Opts, PrometheusError, Registry, U64};
+
+/// Number of recent slots whose first block is kept to detect equivocations
+const SLOT_HISTORY: usize = 4096;
+
+/// Health of an Aura authority since the node started
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorityHealth {
+	/// Aura public key of the authority
+	pub authority: Bytes,
+	/// Best blocks authored
+	pub authored_blocks: u64,
+	/// Slots passed without a best block by the authority
+	pub missed_slots: u64,
+	/// Blocks authored for a slot the authority already authored a block for
+	pub equivocations: u64,
+	/// Slot of the last best block authored
+	pub last_authored_slot: Option<u64>,
+}
+
+#[derive(Default)]
+struct Counts {
+	authored_blocks: u64,
+	missed_slots: u64,
+	equivocations: u64,
+	last_authored_slot: Option<u64>,
+}
+
+struct Metrics {
+	authored_blocks: CounterVec<U64>,
+	missed_slots: CounterVec<U64>,
+	equivocations: CounterVec<U64>,
+}
+
+impl Metrics {
+	fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		let counter = |name: &str, help: &str| -> Result<CounterVec<U64>, PrometheusError> {
+			register(CounterVec::new(Opts::new(name, help), &["authority"])?, registry)
+		};
+
+		Ok(Self {
+			authored_blocks: counter(
+				"aura_authored_blocks_total",
+				"Best blocks authored per Aura authority",
+			)?,
+			missed_slots: counter(
+				"aura_missed_slots_total",
+				"Slots passed without a best block per Aura authority",
+			)?,
+			equivocations: counter(
+				"aura_equivocations_total",
+				"Blocks authored for an already authored slot per Aura authority",
+			)?,
+		})
+	}
+}
+
+/// Counts of the Aura authorities, shared by the task following imports and the RPC
+#[derive(Clone)]
+pub struct AuraMonitor {
+	counts: Arc<RwLock<HashMap<AuraId, Counts>>>,
+	metrics: Option<Arc<Metrics>>,
+}
+
+impl AuraMonitor {
+	/// Creates a monitor, its metrics are registered when a Prometheus registry is given
+	pub fn new(registry: Option<&Registry>) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			counts: Default::default(),
+			metrics: registry.map(Metrics::register).transpose()?.map(Arc::new),
+		})
+	}
+
+	/// Follows the blocks imported by `client`, the returned future runs as long as the node
+	pub fn run<B, C>(self, client: Arc<C>) -> impl Future<Output = ()>
+	where
+		B: BlockT,
+		C: BlockchainEvents<B> + HeaderBackend<B> + ProvideRuntimeApi<B> + Send + Sync + 'static,
+		C::Api: AuraApi<B, AuraId>,
+	{
+		async move {
+			let mut slots = BTreeMap::new();
+			let mut imports = client.import_notification_stream();
+
+			while let Some(import) = imports.next().await {
+				self.note_import(&*client, &import.header, import.is_new_best, &mut slots);
+			}
+		}
+	}
+
+	fn note_import<B, C>(
+		&self,
+		client: &C,
+		header: &B::Header,
+		is_new_best: bool,
+		slots: &mut BTreeMap<u64, B::Hash>,
+	) where
+		B: BlockT,
+		C: HeaderBackend<B> + ProvideRuntimeApi<B>,
+		C::Api: AuraApi<B, AuraId>,
+	{
+		let slot = match slot_of::<B>(header) {
+			| Some(slot) => slot,
+			| None => return,
+		};
+		let parent = BlockId::Hash(*header.parent_hash());
+		let authorities = match client.runtime_api().authorities(&parent) {
+			| Ok(authorities) if !authorities.is_empty() => authorities,
+			| _ => return,
+		};
+		let parent_slot = match is_new_best {
+			| true => client.header(parent).ok().flatten().and_then(|parent| slot_of::<B>(&parent)),
+			| false => None,
+		};
+
+		self.note_slot(&authorities, slot, header.hash(), parent_slot, is_new_best, slots);
+	}
+
+	/// Counts a block of `slot` authored by the slot's author among `authorities`, missed slots
+	/// are counted from `parent_slot` to best blocks
+	fn note_slot<H: PartialEq>(
+		&self,
+		authorities: &[AuraId],
+		slot: u64,
+		hash: H,
+		parent_slot: Option<u64>,
+		is_new_best: bool,
+		slots: &mut BTreeMap<u64, H>,
+	) {
+		let author_of = |slot: u64| &authorities[(slot % authorities.len() as u64) as usize];
+
+		// Only the slot's author can seal a block for it, a second block is an equivocation
+		match slots.entry(slot) {
+			| Entry::Occupied(first) if *first.get() != hash => self.count(
+				author_of(slot),
+				1,
+				|counts| &mut counts.equivocations,
+				|metrics| &metrics.equivocations,
+			),
+			| Entry::Occupied(_) => {},
+			| Entry::Vacant(first) => {
+				first.insert(hash);
+			},
+		}
+		while slots.len() > SLOT_HISTORY {
+			let oldest = *slots.keys().next().expect("slots is not empty; qed");
+			slots.remove(&oldest);
+		}
+
+		if !is_new_best {
+			return
+		}
+
+		self.count(
+			author_of(slot),
+			1,
+			|counts| &mut counts.authored_blocks,
+			|metrics| &metrics.authored_blocks,
+		);
+		self.counts
+			.write()
+			.entry(author_of(slot).clone())
+			.or_default()
+			.last_authored_slot = Some(slot);
+
+		// Slots between the parent's and this block's were missed by their authors
+		if let Some(parent_slot) = parent_slot {
+			let authority_count = authorities.len() as u64;
+			let gap = slot.saturating_sub(parent_slot + 1);
+			for offset in 0..gap.min(authority_count) {
+				let missed = gap / authority_count + u64::from(offset < gap % authority_count);
+				self.count(
+					author_of(parent_slot + 1 + offset),
+					missed,
+					|counts| &mut counts.missed_slots,
+					|metrics| &metrics.missed_slots,
+				);
+			}
+		}
+	}
+
+	fn count(
+		&self,
+		authority: &AuraId,
+		by: u64,
+		counter: impl Fn(&mut Counts) -> &mut u64,
+		metric: impl Fn(&Metrics) -> &CounterVec<U64>,
+	) {
+		*counter(self.counts.write().entry(authority.clone()).or_default()) += by;
+		if let Some(metrics) = &self.metrics {
+			let label = format!("0x{}", HexDisplay::from(&authority.to_raw_vec()));
+			metric(metrics).with_label_values(&[&label]).inc_by(by);
+		}
+	}
+}
+
+/// Slot of an Aura block, read from its pre-runtime digest
+fn slot_of<B: BlockT>(header: &B::Header) -> Option<u64> {
+	header.digest().logs().iter().find_map(|log| {
+		CompatibleDigestItem::<AuraSignature>::as_aura_pre_digest(log).map(u64::from)
+	})
+}
+
+/// Health of the Aura authorities of the network
+#[rpc]
+pub trait AuraMonitorApi {
+	/// Lists the Aura authorities seen since the node started, with the best blocks they
+	/// authored, the slots they missed and their equivocations
+	#[rpc(name = "auraMonitor_authorities")]
+	fn authorities(&self) -> RpcResult<Vec<AuthorityHealth>>;
+}
+
+impl AuraMonitorApi for AuraMonitor {
+	fn authorities(&self) -> RpcResult<Vec<AuthorityHealth>> {
+		let mut authorities: Vec<AuthorityHealth> = self
+			.counts
+			.read()
+			.iter()
+			.map(|(authority, counts)| AuthorityHealth {
+				authority: authority.to_raw_vec().into(),
+				authored_blocks: counts.authored_blocks,
+				missed_slots: counts.missed_slots,
+				equivocations: counts.equivocations,
+				last_authored_slot: counts.last_authored_slot,
+			})
+			.collect();
+		authorities.sort_by(|a, b| a.authority.cmp(&b.authority));
+
+		Ok(authorities)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::{sr25519, H256};
+	use sp_runtime::{Digest, DigestItem};
+	use trackback_node_runtime::opaque::Block;
+
+	type Header = <Block as BlockT>::Header;
+
+	fn authorities(count: u8) -> Vec<AuraId> {
+		(0..count).map(|n| sr25519::Public::from_raw([n; 32]).into()).collect()
+	}
+
+	fn health(monitor: &AuraMonitor, authority: &AuraId) -> (u64, u64, u64, Option<u64>) {
+		let counts = monitor.counts.read();
+		let counts = counts.get(authority).expect("authority is counted");
+		(
+			counts.authored_blocks,
+			counts.missed_slots,
+			counts.equivocations,
+			counts.last_authored_slot,
+		)
+	}
+
+	fn missed_slots(monitor: &AuraMonitor, authorities: &[AuraId]) -> Vec<u64> {
+		let counts = monitor.counts.read();
+		authorities
+			.iter()
+			.map(|authority| counts.get(authority).map_or(0, |counts| counts.missed_slots))
+			.collect()
+	}
+
+	#[test]
+	fn read_the_slot_of_aura_blocks() {
+		let header = |logs| {
+			let digest = Digest { logs };
+			Header::new(1, Default::default(), Default::default(), Default::default(), digest)
+		};
+		let pre_digest: DigestItem<H256> =
+			CompatibleDigestItem::<AuraSignature>::aura_pre_digest(42u64.into());
+
+		assert_eq!(slot_of::<Block>(&header(vec![pre_digest])), Some(42));
+		assert_eq!(slot_of::<Block>(&header(vec![])), None);
+	}
+
+	#[test]
+	fn count_blocks_for_the_author_of_their_slot() {
+		let monitor = AuraMonitor::new(None).unwrap();
+		let authorities = authorities(3);
+		let mut slots = BTreeMap::new();
+
+		monitor.note_slot(&authorities, 7, 1, Some(6), true, &mut slots);
+		monitor.note_slot(&authorities, 8, 2, Some(7), true, &mut slots);
+		monitor.note_slot(&authorities, 10, 3, Some(8), false, &mut slots);
+
+		// Slot 7 is authority 1's, slot 8 authority 2's, blocks off the best chain do not count
+		assert_eq!(health(&monitor, &authorities[1]), (1, 0, 0, Some(7)));
+		assert_eq!(health(&monitor, &authorities[2]), (1, 0, 0, Some(8)));
+		assert!(monitor.counts.read().get(&authorities[0]).is_none());
+	}
+
+	#[test]
+	fn count_missed_slots_across_gaps() {
+		let monitor = AuraMonitor::new(None).unwrap();
+		let authorities = authorities(3);
+		let mut slots = BTreeMap::new();
+
+		// Slots 4 to 11 are missed, authorities 1, 2, 0, 1, 2, 0, 1, 2 in turn
+		monitor.note_slot(&authorities, 12, 1, Some(3), true, &mut slots);
+		assert_eq!(missed_slots(&monitor, &authorities), vec![2, 3, 3]);
+
+		// Slot 13 follows its parent, none are missed
+		monitor.note_slot(&authorities, 13, 2, Some(12), true, &mut slots);
+		assert_eq!(missed_slots(&monitor, &authorities), vec![2, 3, 3]);
+
+		// Slot 15 is authority 0's, authority 2 missed slot 14
+		monitor.note_slot(&authorities, 15, 3, Some(13), true, &mut slots);
+		assert_eq!(missed_slots(&monitor, &authorities), vec![2, 3, 4]);
+
+		// Without a parent slot, e.g. after genesis, nothing is missed
+		monitor.note_slot(&authorities, 20, 4, None, true, &mut slots);
+		assert_eq!(missed_slots(&monitor, &authorities), vec![2, 3, 4]);
+	}
+
+	#[test]
+	fn count_missed_slots_after_a_restart() {
+		let authorities = authorities(2);
+		let monitor = AuraMonitor::new(None).unwrap();
+		let mut slots = BTreeMap::new();
+		monitor.note_slot(&authorities, 5, 1, Some(4), true, &mut slots);
+
+		// Counts start over, the first block counts the slots missed since its stored parent
+		let monitor = AuraMonitor::new(None).unwrap();
+		let mut slots = BTreeMap::new();
+		monitor.note_slot(&authorities, 9, 2, Some(5), true, &mut slots);
+
+		assert_eq!(missed_slots(&monitor, &authorities), vec![2, 1]);
+		assert_eq!(health(&monitor, &authorities[1]), (1, 1, 0, Some(9)));
+	}
+
+	#[test]
+	fn detect_equivocations() {
+		let monitor = AuraMonitor::new(None).unwrap();
+		let authorities = authorities(2);
+		let mut slots = BTreeMap::new();
+
+		monitor.note_slot(&authorities, 3, 1, Some(2), true, &mut slots);
+		// The same header imported again is not an equivocation
+		monitor.note_slot(&authorities, 3, 1, Some(2), false, &mut slots);
+		assert_eq!(health(&monitor, &authorities[1]).2, 0);
+
+		// A second header for slot 3, on the best chain or not
+		monitor.note_slot(&authorities, 3, 2, Some(2), false, &mut slots);
+		monitor.note_slot(&authorities, 3, 3, Some(2), true, &mut slots);
+		assert_eq!(health(&monitor, &authorities[1]), (2, 0, 2, Some(3)));
+		assert_eq!(slots.get(&3), Some(&1));
+	}
+
+	#[test]
+	fn keep_a_bounded_slot_history() {
+		let monitor = AuraMonitor::new(None).unwrap();
+		let authorities = authorities(1);
+		let mut slots = BTreeMap::new();
+
+		for slot in 0..SLOT_HISTORY as u64 + 10 {
+			monitor.note_slot(&authorities, slot, slot, None, false, &mut slots);
+		}
+
+		assert_eq!(slots.len(), SLOT_HISTORY);
+		assert_eq!(slots.keys().next(), Some(&10));
+	}
+}
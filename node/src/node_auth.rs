@@ -0,0 +1,453 @@
+//! Node authorization management.
+//! The `node-auth` subcommand lists the well-known nodes of the permissioned network and manages
+//! them through a node's HTTP RPC: it signs and submits `pallet_node_authorization` extrinsics,
+//! wrapping the admin ones, adding and removing well-known nodes, in `sudo` or a council proposal.
+//! The `nodeAuth_status` and `nodeAuth_wellKnownNodes` RPCs read the pallet's storage at the best
+//! block.
+
+use crate::service::FullClient;
+use codec::{Decode, Encode};
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result as RpcResult};
+use jsonrpc_derive::rpc;
+use sc_client_api::StorageProvider;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use sp_blockchain::HeaderBackend;
+use sp_core::{
+	crypto::{Pair, Ss58Codec},
+	hashing::{blake2_128, twox_128},
+	sr25519,
+	storage::StorageKey,
+	Bytes,
+};
+use sp_runtime::{
+	generic::{BlockId, Era, SignedPayload},
+	traits::{IdentifyAccount, Verify},
+};
+use std::{collections::BTreeSet, sync::Arc, time::Duration};
+use structopt::StructOpt;
+use trackback_node_runtime::{
+	AccountId, Call, CouncilCall, Hash, Index, NodeAuthorizationCall, Runtime, Signature,
+	SignedExtra, SudoCall, UncheckedExtrinsic,
+};
+
+type AccountPublic = <Signature as Verify>::Signer;
+
+/// Peer ID of a node, as stored by `pallet_node_authorization`
+pub type PeerId = Vec<u8>;
+
+/// Storage prefix of `pallet_node_authorization`
+const PALLET_PREFIX: &[u8] = b"NodeAuthorization";
+
+/// Time allowed for a request to the node
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Manage the well-known nodes and connections of the permissioned network
+#[derive(Debug, StructOpt)]
+pub struct NodeAuthCmd {
+	#[structopt(subcommand)]
+	pub action: NodeAuthAction,
+
+	/// HTTP RPC endpoint of the node the command talks to
+	#[structopt(long, default_value = "http://127.0.0.1:9933")]
+	pub url: String,
+}
+
+#[derive(Debug, StructOpt)]
+pub enum NodeAuthAction {
+	/// List the well-known nodes with their owners and additional connections
+	List,
+
+	/// Show the authorization status of the node
+	Status,
+
+	/// Add a well-known node
+	Add {
+		/// Peer ID of the node, base58 encoded
+		#[structopt(parse(try_from_str = parse_peer_id))]
+		peer_id: PeerId,
+		/// Account owning the node, SS58 encoded
+		#[structopt(parse(try_from_str = parse_account))]
+		owner: AccountId,
+		#[structopt(flatten)]
+		admin: AdminParams,
+	},
+
+	/// Remove a well-known node
+	Remove {
+		/// Peer ID of the node, base58 encoded
+		#[structopt(parse(try_from_str = parse_peer_id))]
+		peer_id: PeerId,
+		#[structopt(flatten)]
+		admin: AdminParams,
+	},
+
+	/// Claim a node for the signing account
+	Claim {
+		/// Peer ID of the node, base58 encoded
+		#[structopt(parse(try_from_str = parse_peer_id))]
+		peer_id: PeerId,
+		#[structopt(flatten)]
+		signer: SignerParams,
+	},
+
+	/// Allow nodes to connect to a node owned by the signing account
+	AddConnections {
+		/// Peer ID of the owned node, base58 encoded
+		#[structopt(parse(try_from_str = parse_peer_id))]
+		peer_id: PeerId,
+		/// Peer IDs of the nodes allowed to connect, base58 encoded
+		#[structopt(required = true, parse(try_from_str = parse_peer_id))]
+		connections: Vec<PeerId>,
+		#[structopt(flatten)]
+		signer: SignerParams,
+	},
+
+	/// Stop allowing nodes to connect to a node owned by the signing account
+	RemoveConnections {
+		/// Peer ID of the owned node, base58 encoded
+		#[structopt(parse(try_from_str = parse_peer_id))]
+		peer_id: PeerId,
+		/// Peer IDs of the nodes no longer allowed to connect, base58 encoded
+		#[structopt(required = true, parse(try_from_str = parse_peer_id))]
+		connections: Vec<PeerId>,
+		#[structopt(flatten)]
+		signer: SignerParams,
+	},
+}
+
+#[derive(Debug, StructOpt)]
+pub struct SignerParams {
+	/// Secret URI of the sr25519 account signing the extrinsic, e.g. `//Alice` or a mnemonic
+	#[structopt(long)]
+	pub suri: String,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct AdminParams {
+	#[structopt(flatten)]
+	pub signer: SignerParams,
+
+	/// Dispatch the call through `sudo`, the signer being the sudo key
+	#[structopt(long, conflicts_with = "threshold")]
+	pub sudo: bool,
+
+	/// Propose the call to the council, executed once this many members approve it. Admin calls
+	/// need two thirds of the council
+	#[structopt(long, required_unless = "sudo")]
+	pub threshold: Option<u32>,
+}
+
+impl NodeAuthCmd {
+	/// Runs the command against the node at `url`
+	pub fn run(&self) -> sc_cli::Result<()> {
+		let node = NodeRpc::new(&self.url);
+
+		let output = match &self.action {
+			| NodeAuthAction::List => node.call::<Value>("nodeAuth_wellKnownNodes", json!([])),
+			| NodeAuthAction::Status => node.call::<Value>("nodeAuth_status", json!([])),
+			| NodeAuthAction::Add { peer_id, owner, admin } => node.submit_admin(
+				NodeAuthorizationCall::add_well_known_node(peer_id.clone(), owner.clone()),
+				admin,
+			),
+			| NodeAuthAction::Remove { peer_id, admin } => node.submit_admin(
+				NodeAuthorizationCall::remove_well_known_node(peer_id.clone()),
+				admin,
+			),
+			| NodeAuthAction::Claim { peer_id, signer } => node.submit(
+				Call::NodeAuthorization(NodeAuthorizationCall::claim_node(peer_id.clone())),
+				signer,
+			),
+			| NodeAuthAction::AddConnections { peer_id, connections, signer } => node.submit(
+				Call::NodeAuthorization(NodeAuthorizationCall::add_connections(
+					peer_id.clone(),
+					connections.clone(),
+				)),
+				signer,
+			),
+			| NodeAuthAction::RemoveConnections { peer_id, connections, signer } => node.submit(
+				Call::NodeAuthorization(NodeAuthorizationCall::remove_connections(
+					peer_id.clone(),
+					connections.clone(),
+				)),
+				signer,
+			),
+		}
+		.map_err(sc_cli::Error::Input)?;
+
+		println!("{}", serde_json::to_string_pretty(&output).expect("JSON values serialize; qed"));
+		Ok(())
+	}
+}
+
+/// Wraps an admin call, adding or removing well-known nodes, in `sudo` or a council proposal
+fn admin_call(call: NodeAuthorizationCall<Runtime>, admin: &AdminParams) -> Call {
+	let call = Call::NodeAuthorization(call);
+
+	match admin.threshold {
+		| Some(threshold) if !admin.sudo => {
+			let length_bound = call.encoded_size() as u32;
+			Call::Council(CouncilCall::propose(threshold, Box::new(call), length_bound))
+		},
+		| _ => Call::Sudo(SudoCall::sudo(Box::new(call))),
+	}
+}
+
+/// Decodes a base58 peer ID, e.g. `12D3KooW...`
+pub fn parse_peer_id(peer_id: &str) -> Result<PeerId, String> {
+	bs58::decode(peer_id)
+		.into_vec()
+		.map_err(|e| format!("Invalid peer ID {}: {}", peer_id, e))
+}
+
+fn parse_account(account: &str) -> Result<AccountId, String> {
+	AccountId::from_ss58check(account).map_err(|e| format!("Invalid account {}: {:?}", account, e))
+}
+
+#[derive(Deserialize)]
+struct Response<T> {
+	result: Option<T>,
+	error: Option<Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Version {
+	spec_version: u32,
+	transaction_version: u32,
+}
+
+/// HTTP RPC client of a node
+struct NodeRpc {
+	url: String,
+	agent: ureq::Agent,
+}
+
+impl NodeRpc {
+	fn new(url: &str) -> Self {
+		Self {
+			url: url.to_string(),
+			agent: ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build(),
+		}
+	}
+
+	fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, String> {
+		let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+
+		let response = self
+			.agent
+			.post(&self.url)
+			.set("Content-Type", "application/json")
+			.send_string(&body.to_string())
+			.map_err(|e| format!("Unable to reach {}: {}", self.url, e))?
+			.into_string()
+			.map_err(|e| format!("Unable to read the response to {}: {}", method, e))?;
+		let response: Response<T> = serde_json::from_str(&response)
+			.map_err(|e| format!("Invalid response to {}: {}", method, e))?;
+
+		match (response.result, response.error) {
+			| (_, Some(error)) => Err(format!("{} failed: {}", method, error)),
+			| (Some(result), None) => Ok(result),
+			| (None, None) => Err(format!("{} returned no result", method)),
+		}
+	}
+
+	fn submit_admin(
+		&self,
+		call: NodeAuthorizationCall<Runtime>,
+		admin: &AdminParams,
+	) -> Result<Value, String> {
+		self.submit(admin_call(call, admin), &admin.signer)
+	}
+
+	/// Signs `call` with an immortal era and submits it, returning the extrinsic hash
+	fn submit(&self, call: Call, signer: &SignerParams) -> Result<Value, String> {
+		let pair = sr25519::Pair::from_string(&signer.suri, None)
+			.map_err(|e| format!("Invalid secret URI: {:?}", e))?;
+		let account: AccountId = AccountPublic::from(pair.public()).into_account();
+
+		let genesis: Hash = self.call("chain_getBlockHash", json!([0]))?;
+		let version: Version = self.call("state_getRuntimeVersion", json!([]))?;
+		let nonce: Index = self.call("system_accountNextIndex", json!([account]))?;
+
+		let extra: SignedExtra = (
+			frame_system::CheckSpecVersion::new(),
+			frame_system::CheckTxVersion::new(),
+			frame_system::CheckGenesis::new(),
+			frame_system::CheckEra::from(Era::Immortal),
+			frame_system::CheckNonce::from(nonce),
+			frame_system::CheckWeight::new(),
+			pallet_transaction_payment::ChargeTransactionPayment::from(0),
+			pallet_dids::CheckDidAuthorization::new(),
+			pallet_dids::CheckDidOperationLimits::new(),
+		);
+		let payload = SignedPayload::from_raw(
+			call,
+			extra,
+			(
+				version.spec_version,
+				version.transaction_version,
+				genesis,
+				genesis,
+				(),
+				(),
+				(),
+				(),
+				(),
+			),
+		);
+		let signature = payload.using_encoded(|payload| pair.sign(payload));
+		let (call, extra, _) = payload.deconstruct();
+		let extrinsic =
+			UncheckedExtrinsic::new_signed(call, account.into(), signature.into(), extra);
+
+		self.call("author_submitExtrinsic", json!([Bytes(extrinsic.encode())]))
+	}
+}
+
+/// Authorization of a node
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeStatus {
+	/// Peer ID of the node, base58 encoded
+	pub peer_id: String,
+	/// Whether the node is well-known, i.e. connected to every other well-known node
+	pub well_known: bool,
+	/// Account that claimed the node
+	pub owner: Option<AccountId>,
+	/// Nodes the owner allows to connect to the node, base58 encoded
+	pub connections: Vec<String>,
+}
+
+/// Authorization of the nodes of the permissioned network
+#[rpc]
+pub trait NodeAuthorizationApi {
+	/// Shows whether this node is well-known, its owner and its additional connections
+	#[rpc(name = "nodeAuth_status")]
+	fn status(&self) -> RpcResult<NodeStatus>;
+
+	/// Lists the well-known nodes with their owners and additional connections
+	#[rpc(name = "nodeAuth_wellKnownNodes")]
+	fn well_known_nodes(&self) -> RpcResult<Vec<NodeStatus>>;
+}
+
+/// Reads the authorization of nodes from the best block of the client
+#[derive(Clone)]
+pub struct NodeAuthorization {
+	client: Arc<FullClient>,
+	peer_id: PeerId,
+}
+
+impl NodeAuthorization {
+	/// Creates the RPC of the node with `peer_id`
+	pub fn new(client: Arc<FullClient>, peer_id: PeerId) -> Self {
+		Self { client, peer_id }
+	}
+
+	fn storage<T: Decode>(&self, key: Vec<u8>) -> RpcResult<Option<T>> {
+		let best = BlockId::Hash(self.client.info().best_hash);
+
+		self.client
+			.storage(&best, &StorageKey(key))
+			.map_err(storage_error)?
+			.map(|data| T::decode(&mut &data.0[..]))
+			.transpose()
+			.map_err(storage_error)
+	}
+
+	fn well_known(&self) -> RpcResult<BTreeSet<PeerId>> {
+		Ok(self.storage(value_key(b"WellKnownNodes"))?.unwrap_or_default())
+	}
+
+	fn node_status(&self, peer_id: &PeerId, well_known: bool) -> RpcResult<NodeStatus> {
+		let connections: BTreeSet<PeerId> =
+			self.storage(map_key(b"AdditionalConnections", peer_id))?.unwrap_or_default();
+
+		Ok(NodeStatus {
+			peer_id: bs58::encode(peer_id).into_string(),
+			well_known,
+			owner: self.storage(map_key(b"Owners", peer_id))?,
+			connections: connections.iter().map(|peer| bs58::encode(peer).into_string()).collect(),
+		})
+	}
+}
+
+impl NodeAuthorizationApi for NodeAuthorization {
+	fn status(&self) -> RpcResult<NodeStatus> {
+		self.node_status(&self.peer_id, self.well_known()?.contains(&self.peer_id))
+	}
+
+	fn well_known_nodes(&self) -> RpcResult<Vec<NodeStatus>> {
+		self.well_known()?
+			.iter()
+			.map(|peer_id| self.node_status(peer_id, true))
+			.collect()
+	}
+}
+
+/// Storage key of a value of the pallet
+fn value_key(item: &[u8]) -> Vec<u8> {
+	let mut key = twox_128(PALLET_PREFIX).to_vec();
+	key.extend_from_slice(&twox_128(item));
+	key
+}
+
+/// Storage key of a node in a `blake2_128_concat` map of the pallet
+fn map_key(item: &[u8], peer_id: &PeerId) -> Vec<u8> {
+	let mut key = value_key(item);
+	let encoded = peer_id.encode();
+	key.extend_from_slice(&blake2_128(&encoded));
+	key.extend(encoded);
+	key
+}
+
+fn storage_error(e: impl std::fmt::Debug) -> RpcError {
+	RpcError {
+		code: ErrorCode::InternalError,
+		message: "Unable to read the node authorization storage.".into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const PEER_ID: &str = "12D3KooWBmAwcd4PJNJvfV89HwE48nwkRmAgo8Vy3uQEyNNHBox2";
+
+	fn admin(sudo: bool, threshold: Option<u32>) -> AdminParams {
+		AdminParams { signer: SignerParams { suri: "//Alice".into() }, sudo, threshold }
+	}
+
+	#[test]
+	fn wrap_admin_calls_in_sudo_or_a_council_proposal() {
+		let peer_id = parse_peer_id(PEER_ID).unwrap();
+		let call = || NodeAuthorizationCall::remove_well_known_node(peer_id.clone());
+		let inner = Call::NodeAuthorization(call());
+
+		assert_eq!(
+			admin_call(call(), &admin(true, None)),
+			Call::Sudo(SudoCall::sudo(Box::new(inner.clone())))
+		);
+		assert_eq!(
+			admin_call(call(), &admin(false, Some(2))),
+			Call::Council(CouncilCall::propose(
+				2,
+				Box::new(inner.clone()),
+				inner.encoded_size() as u32
+			))
+		);
+	}
+
+	#[test]
+	fn key_storage_like_the_pallet() {
+		let peer_id = parse_peer_id(PEER_ID).unwrap();
+		assert_eq!(bs58::encode(&peer_id).into_string(), PEER_ID);
+		assert!(parse_peer_id("0OIl").is_err());
+
+		let key = map_key(b"Owners", &peer_id);
+		assert_eq!(&key[..32], &value_key(b"Owners")[..]);
+		assert_eq!(&key[32..48], &blake2_128(&peer_id.encode())[..]);
+		assert_eq!(PeerId::decode(&mut &key[48..]).unwrap(), peer_id);
+	}
+}
@@ -0,0 +1,167 @@
+// This file is part of TrackBck which is released under GNU General Public License v3.0.
+// See file LICENSE.md or go to https://www.gnu.org/licenses/gpl-3.0.en.html for full license details.
+//! Runtime API definition for the DID pallet
+//! * Exposes the verifiable credential fingerprints anchored by issuers
+//! * Lists issuers accredited per credential type
+//! * Verifies inclusion of credential hashes in anchored Merkle roots
+//! * Lists DIDs by controller account and by public key
+//! * Resolves a DID as it is stored on chain
+//! * Answers whether a DID is active, whether a message is signed by one of its keys and what the
+//!   status of a verifiable credential is, so clients need not decode storage
+//! * Keys DID documents indexed off chain, for the pallet writing them and the RPC reading them
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Codec, Decode, Encode};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_runtime::RuntimeDebug;
+use sp_std::vec::Vec;
+
+/// Offchain indexing prefix of DID documents
+pub const DID_DOCUMENT_PREFIX: &[u8] = b"trackback::dids::document";
+
+/// Offchain indexing key of a DID document version
+pub fn document_key(did_uri: &[u8], version: u32) -> Vec<u8> {
+	(DID_DOCUMENT_PREFIX, did_uri, version).encode()
+}
+
+/// Fingerprint of a verifiable credential as it is stored on chain
+#[derive(Clone, Decode, Encode, Eq, PartialEq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct CredentialFingerprint<AccountId> {
+	// Issuer/Controller's AccountId
+	pub issuer: Option<AccountId>,
+
+	// Holder's public key
+	pub holder_public_key: Vec<u8>,
+
+	// Credential status
+	pub active: Option<bool>,
+
+	// Created time
+	pub block_time_stamp: u64,
+
+	// Hash of the registered schema the credential conforms to
+	pub schema_id: Option<Vec<u8>>,
+
+	// Issuer's DID URI
+	pub issuer_did: Option<Vec<u8>>,
+
+	// Credential type the issuer is accredited for
+	pub credential_type: Option<Vec<u8>>,
+}
+
+/// Merkle root anchored over a batch of verifiable credential hashes
+#[derive(Clone, Decode, Encode, Eq, PartialEq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct CredentialBatch<AccountId> {
+	// Issuer's AccountId which anchored the batch
+	pub issuer: AccountId,
+
+	// Issuer's DID URI
+	pub issuer_did: Option<Vec<u8>>,
+
+	// Credential type the issuer is accredited for
+	pub credential_type: Option<Vec<u8>>,
+
+	// Number of verifiable credential hashes in the batch
+	pub leaf_count: u32,
+
+	// Created time
+	pub block_time_stamp: u64,
+}
+
+/// DID as it is stored on chain, the document itself lives off chain
+#[derive(Clone, Decode, Encode, Eq, PartialEq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct DIDRecord<AccountId> {
+	// Controller's AccountId
+	pub controller: Option<AccountId>,
+
+	// DID Resolution Metadata
+	pub did_resolution_metadata: Option<Vec<u8>>,
+
+	// DID Document Metadata
+	pub did_document_metadata: Option<Vec<u8>>,
+
+	// IPFS CID of the DID document
+	pub did_ref: Option<Vec<u8>>,
+
+	// Blake2 256 hash of the DID document
+	pub did_document_hash: Vec<u8>,
+
+	// Public keys signing the DID document
+	pub public_keys: Vec<Vec<u8>>,
+
+	// Version of the DID document, incremented on every update
+	pub version: u32,
+
+	// Created time
+	pub block_time_stamp: u64,
+
+	// Updated time
+	pub updated_time_stamp: u64,
+}
+
+/// Status of a verifiable credential as of the queried block
+#[derive(Clone, Decode, Encode, Eq, PartialEq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct CredentialStatus {
+	// Status anchored with the fingerprint
+	pub active: Option<bool>,
+
+	// Whether the issuer's DID exists, `None` without an issuer DID
+	pub issuer_did_active: Option<bool>,
+
+	// Whether the issuer is accredited for the credential type, `None` without one
+	pub issuer_accredited: Option<bool>,
+
+	// Neither revoked, nor issued by a missing or unaccredited issuer
+	pub valid: bool,
+}
+
+sp_api::decl_runtime_apis! {
+	#[api_version(2)]
+	pub trait DIDApi<AccountId> where
+		AccountId: Codec,
+	{
+		/// Returns the fingerprint anchored for a verifiable credential hash
+		fn vc_fingerprint(vc_hash: Vec<u8>) -> Option<CredentialFingerprint<AccountId>>;
+
+		/// Returns the DID URIs of issuers accredited for a credential type
+		fn trusted_issuers(credential_type: Vec<u8>) -> Vec<Vec<u8>>;
+
+		/// Returns the anchored batch when the proof includes the hash under the Merkle root
+		fn verify_batch_inclusion(
+			merkle_root: Vec<u8>,
+			vc_hash: Vec<u8>,
+			proof: Vec<Vec<u8>>,
+		) -> Option<CredentialBatch<AccountId>>;
+
+		/// Returns the DID URIs controlled by an account
+		fn dids_by_controller(controller: AccountId) -> Vec<Vec<u8>>;
+
+		/// Returns the DID URIs signed by a public key
+		fn dids_by_public_key(public_key: Vec<u8>) -> Vec<Vec<u8>>;
+
+		/// Returns the DID stored for a DID URI
+		fn resolve(did_uri: Vec<u8>) -> Option<DIDRecord<AccountId>>;
+
+		/// Returns whether a DID is stored and not revoked
+		fn is_active(did_uri: Vec<u8>) -> bool;
+
+		/// Returns whether an ed25519 signature over a message is made by an active key of an
+		/// active DID
+		fn verify(
+			did_uri: Vec<u8>,
+			public_key: Vec<u8>,
+			message: Vec<u8>,
+			signature: Vec<u8>,
+		) -> bool;
+
+		/// Returns the status of a verifiable credential hash
+		fn vc_status(vc_hash: Vec<u8>) -> Option<CredentialStatus>;
+	}
+}
@@ -0,0 +1,226 @@
+// This file is part of TrackBck which is released under GNU General Public License v3.0.
+// See file LICENSE.md or go to https://www.gnu.org/licenses/gpl-3.0.en.html for full license details.
+//! Canonicalisation and hashing of presented verifiable credentials
+//! * JSON credentials are canonicalised with the JSON Canonicalization Scheme (JCS, RFC 8785),
+//!   issuers must apply the same scheme before hashing
+//! * JWT credentials are hashed in their compact serialisation
+//! * Hashes are Blake2 256, the same algorithm issuers use for `create_vc_fingerprint`
+
+use serde_json::{Number, Value};
+use sp_core::hashing::blake2_256;
+
+/// Reasons a presented credential can not be canonicalised
+#[derive(Debug, PartialEq, Eq)]
+pub enum CredentialError {
+	/// Credential is neither a JSON object nor a compact JWT
+	UnsupportedFormat,
+
+	/// Credential looks like JSON but could not be parsed
+	InvalidJson(String),
+}
+
+impl std::fmt::Display for CredentialError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			| CredentialError::UnsupportedFormat =>
+				write!(f, "Credential must be a JSON object or a compact JWT"),
+			| CredentialError::InvalidJson(e) => write!(f, "Invalid JSON credential: {}", e),
+		}
+	}
+}
+
+/// Returns the canonical byte representation of a presented credential
+pub fn canonicalise(credential: &str) -> Result<Vec<u8>, CredentialError> {
+	let credential = credential.trim();
+
+	if credential.starts_with('{') {
+		let value: Value = serde_json::from_str(credential)
+			.map_err(|e| CredentialError::InvalidJson(e.to_string()))?;
+		let mut canonical = String::new();
+		write_canonical(&value, &mut canonical);
+		return Ok(canonical.into_bytes())
+	}
+
+	if is_compact_jwt(credential) {
+		return Ok(credential.as_bytes().to_vec())
+	}
+
+	Err(CredentialError::UnsupportedFormat)
+}
+
+/// Hashes a presented credential the same way issuers do before anchoring it
+pub fn credential_hash(credential: &str) -> Result<[u8; 32], CredentialError> {
+	canonicalise(credential).map(|canonical| blake2_256(&canonical))
+}
+
+/// Compact JWTs are three base64url segments separated by dots
+fn is_compact_jwt(credential: &str) -> bool {
+	let segments: Vec<&str> = credential.split('.').collect();
+	segments.len() == 3 &&
+		segments[..2].iter().all(|s| !s.is_empty()) &&
+		segments
+			.iter()
+			.all(|s| s.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+}
+
+/// Serialises a JSON value as JCS: object keys sorted by their UTF-16 code units, numbers as
+/// ECMAScript doubles and strings with the minimal JSON escapes
+fn write_canonical(value: &Value, out: &mut String) {
+	match value {
+		| Value::Object(map) => {
+			let mut keys: Vec<&String> = map.keys().collect();
+			keys.sort_by(|a, b| a.encode_utf16().cmp(b.encode_utf16()));
+
+			out.push('{');
+			for (i, key) in keys.into_iter().enumerate() {
+				if i > 0 {
+					out.push(',');
+				}
+				out.push_str(&Value::String(key.clone()).to_string());
+				out.push(':');
+				write_canonical(&map[key], out);
+			}
+			out.push('}');
+		},
+		| Value::Array(items) => {
+			out.push('[');
+			for (i, item) in items.iter().enumerate() {
+				if i > 0 {
+					out.push(',');
+				}
+				write_canonical(item, out);
+			}
+			out.push(']');
+		},
+		| Value::Number(number) => write_number(number, out),
+		// serde_json escapes `"`, `\\` and control characters only, with lowercase `\u00xx`
+		| other => out.push_str(&other.to_string()),
+	}
+}
+
+/// Serialises a number the way ECMAScript's `Number.prototype.toString` does
+fn write_number(number: &Number, out: &mut String) {
+	let value = number.as_f64().unwrap_or_default();
+	if value == 0.0 {
+		return out.push('0')
+	}
+	if value < 0.0 {
+		out.push('-');
+	}
+
+	// Shortest round-trip digits `d1...dk` of the value `0.d1...dk * 10^n`
+	let scientific = format!("{:e}", value.abs());
+	let (mantissa, exponent) =
+		scientific.split_at(scientific.find('e').expect("`{:e}` has an e; qed"));
+	let digits = mantissa.replace('.', "");
+	let k = digits.len() as i32;
+	let n = exponent[1..].parse::<i32>().expect("`{:e}` exponents are integers; qed") + 1;
+
+	match n {
+		| n if k <= n && n <= 21 => {
+			out.push_str(&digits);
+			out.extend(std::iter::repeat('0').take((n - k) as usize));
+		},
+		| n if 0 < n && n <= 21 => {
+			out.push_str(&digits[..n as usize]);
+			out.push('.');
+			out.push_str(&digits[n as usize..]);
+		},
+		| n if -6 < n && n <= 0 => {
+			out.push_str("0.");
+			out.extend(std::iter::repeat('0').take(-n as usize));
+			out.push_str(&digits);
+		},
+		| n => {
+			out.push_str(&digits[..1]);
+			if k > 1 {
+				out.push('.');
+				out.push_str(&digits[1..]);
+			}
+			out.push_str(&format!("e{}{}", if n > 0 { '+' } else { '-' }, (n - 1).abs()));
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn json_credentials_ignore_key_order_and_whitespace() {
+		let a = r#"{"issuer": "did:trackback:1", "credentialSubject": {"name": "A", "age": 1}}"#;
+		let b = r#"{
+			"credentialSubject": { "age": 1, "name": "A" },
+			"issuer": "did:trackback:1"
+		}"#;
+
+		assert_eq!(canonicalise(a), canonicalise(b));
+		assert_eq!(
+			canonicalise(a).unwrap(),
+			br#"{"credentialSubject":{"age":1,"name":"A"},"issuer":"did:trackback:1"}"#.to_vec()
+		);
+	}
+
+	#[test]
+	fn json_credentials_follow_rfc_8785() {
+		// Example of RFC 8785 section 3.2.4
+		let credential = r#"{
+			"numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+			"string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+			"literals": [null, true, false]
+		}"#;
+
+		assert_eq!(
+			String::from_utf8(canonicalise(credential).unwrap()).unwrap(),
+			r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+		);
+	}
+
+	#[test]
+	fn json_numbers_are_ecmascript_doubles() {
+		let numbers = [
+			("0", "0"),
+			("-0", "0"),
+			("100", "100"),
+			("1e20", "100000000000000000000"),
+			("1e21", "1e+21"),
+			("-1.5", "-1.5"),
+			("0.000001", "0.000001"),
+			("1e-7", "1e-7"),
+			("9007199254740993", "9007199254740992"),
+			("5e-324", "5e-324"),
+			("-1.7976931348623157e308", "-1.7976931348623157e+308"),
+		];
+
+		for (number, canonical) in numbers.iter() {
+			let mut out = String::new();
+			write_canonical(&serde_json::from_str(number).unwrap(), &mut out);
+			assert_eq!(&out, canonical, "{}", number);
+		}
+	}
+
+	#[test]
+	fn json_keys_sort_by_utf16_code_units() {
+		// U+1F600 (surrogates D83D DE00) sorts before U+FB33 in UTF-16, after it in UTF-8
+		let credential = "{\"\u{fb33}\": 1, \"\u{1f600}\": 2}";
+
+		assert_eq!(
+			String::from_utf8(canonicalise(credential).unwrap()).unwrap(),
+			"{\"\u{1f600}\":2,\"\u{fb33}\":1}"
+		);
+	}
+
+	#[test]
+	fn jwt_credentials_are_hashed_as_presented() {
+		let jwt = "eyJhbGciOiJFZERTQSJ9.eyJ2YyI6e319.c2lnbmF0dXJl";
+
+		assert_eq!(canonicalise(jwt).unwrap(), jwt.as_bytes().to_vec());
+		assert_eq!(credential_hash(jwt).unwrap(), blake2_256(jwt.as_bytes()));
+	}
+
+	#[test]
+	fn rejects_unsupported_credentials() {
+		assert_eq!(canonicalise("not a credential"), Err(CredentialError::UnsupportedFormat));
+		assert!(matches!(canonicalise("{ broken"), Err(CredentialError::InvalidJson(_))));
+	}
+}
@@ -0,0 +1,40 @@
+// This file is part of TrackBck which is released under GNU General Public License v3.0.
+// See file LICENSE.md or go to https://www.gnu.org/licenses/gpl-3.0.en.html for full license details.
+//! Fetches DID documents referenced by `did_ref` from a local IPFS HTTP gateway
+//! * Documents are requested from `<gateway>/ipfs/<cid>`
+//! * Callers verify the fetched bytes against the anchored document hash
+
+use std::{io::Read, time::Duration};
+
+/// Time allowed to fetch a DID document
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Largest DID document accepted from the gateway
+const MAX_DOCUMENT_SIZE: u64 = 1024 * 1024;
+
+/// Fetches the document addressed by a CID, optionally `ipfs://` prefixed
+pub fn fetch(gateway: &str, did_ref: &[u8]) -> Result<Vec<u8>, String> {
+	let cid = std::str::from_utf8(did_ref).map_err(|e| e.to_string())?;
+	let cid = cid.strip_prefix("ipfs://").unwrap_or(cid);
+	let url = format!("{}/ipfs/{}", gateway.trim_end_matches('/'), cid);
+
+	let response = ureq::AgentBuilder::new()
+		.timeout(FETCH_TIMEOUT)
+		.build()
+		.get(&url)
+		.call()
+		.map_err(|e| e.to_string())?;
+
+	let mut document = Vec::new();
+	response
+		.into_reader()
+		.take(MAX_DOCUMENT_SIZE + 1)
+		.read_to_end(&mut document)
+		.map_err(|e| e.to_string())?;
+
+	if document.len() as u64 > MAX_DOCUMENT_SIZE {
+		return Err(format!("DID document exceeds {} bytes", MAX_DOCUMENT_SIZE))
+	}
+
+	Ok(document)
+}
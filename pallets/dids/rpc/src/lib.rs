@@ -0,0 +1,434 @@
+// This file is part of TrackBck which is released under GNU General Public License v3.0.
+// See file LICENSE.md or go to https://www.gnu.org/licenses/gpl-3.0.en.html for full license details.
+//! RPC interface for the DID pallet
+//! * Verifies a presented verifiable credential against its anchored fingerprint
+//! * Lists issuers accredited for a credential type
+//! * Verifies inclusion proofs of credential hashes against anchored Merkle roots
+//! * Lists DIDs by controller account and by public key
+//! * Resolves DIDs, reading their documents from the offchain DB or an IPFS gateway
+//! * Checks DIDs, signatures by DID keys and credential status through the runtime
+
+use std::{marker::PhantomData, sync::Arc};
+
+use codec::Codec;
+use futures::{executor::ThreadPool, task::SpawnExt, FutureExt, TryFutureExt};
+use jsonrpc_core::{BoxFuture, Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use serde::{Deserialize, Serialize};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::{hashing::blake2_256, offchain::OffchainStorage, Bytes};
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+use pallet_dids_runtime_api::document_key;
+pub use pallet_dids_runtime_api::{CredentialStatus, DIDApi as DIDRuntimeApi};
+
+pub mod credential;
+pub mod ipfs;
+
+/// Error code for failures while calling into the runtime
+const RUNTIME_ERROR: i64 = 1;
+
+/// Error code for credentials that can not be canonicalised
+const INVALID_CREDENTIAL: i64 = 2;
+
+/// Error code for DID documents that can not be fetched or do not match the anchored hash
+const INVALID_DOCUMENT: i64 = 3;
+
+/// Result of verifying a presented credential against the chain
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifiedCredential<AccountId> {
+	/// Hash computed from the presented credential
+	pub vc_hash: Bytes,
+	/// Issuer/Controller's account that anchored the fingerprint
+	pub issuer: Option<AccountId>,
+	/// Holder's public key
+	pub holder_public_key: Bytes,
+	/// Credential status
+	pub active: Option<bool>,
+	/// Anchored time stamp
+	pub block_time_stamp: u64,
+	/// Hash of the registered schema the credential conforms to
+	pub schema_id: Option<Bytes>,
+	/// Issuer's DID URI
+	pub issuer_did: Option<Bytes>,
+	/// Credential type the issuer is accredited for
+	pub credential_type: Option<Bytes>,
+}
+
+/// Anchored batch a credential hash is proven to be included in
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifiedBatch<AccountId> {
+	/// Merkle root of the batch
+	pub merkle_root: Bytes,
+	/// Issuer/Controller's account that anchored the batch
+	pub issuer: AccountId,
+	/// Issuer's DID URI
+	pub issuer_did: Option<Bytes>,
+	/// Credential type the issuer is accredited for
+	pub credential_type: Option<Bytes>,
+	/// Number of credential hashes in the batch
+	pub leaf_count: u32,
+	/// Anchored time stamp
+	pub block_time_stamp: u64,
+}
+
+/// DID resolved from the chain, with its document when it is indexed or fetched
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedDID<AccountId> {
+	/// DID URI
+	pub did_uri: Bytes,
+	/// Controller's account
+	pub controller: Option<AccountId>,
+	/// DID document read from the offchain DB or fetched by its `didRef`
+	pub did_document: Option<serde_json::Value>,
+	/// Blake2 256 hash of the DID document
+	pub did_document_hash: Bytes,
+	/// DID document metadata
+	pub did_document_metadata: Option<Bytes>,
+	/// DID resolution metadata
+	pub did_resolution_metadata: Option<Bytes>,
+	/// IPFS CID of the DID document
+	pub did_ref: Option<Bytes>,
+	/// Public keys signing the DID document
+	pub public_keys: Vec<Bytes>,
+	/// Version of the DID document
+	pub version: u32,
+	/// Created time stamp
+	pub block_time_stamp: u64,
+	/// Updated time stamp
+	pub updated_time_stamp: u64,
+}
+
+#[rpc]
+pub trait DIDApi<BlockHash, AccountId> {
+	/// Canonicalises a JSON or JWT verifiable credential, hashes it and returns its fingerprint
+	#[rpc(name = "did_verifyCredential")]
+	fn verify_credential(
+		&self,
+		credential: String,
+		at: Option<BlockHash>,
+	) -> Result<Option<VerifiedCredential<AccountId>>>;
+
+	/// Lists the DID URIs of issuers accredited for a credential type
+	#[rpc(name = "did_trustedIssuers")]
+	fn trusted_issuers(&self, credential_type: Bytes, at: Option<BlockHash>) -> Result<Vec<Bytes>>;
+
+	/// Verifies a Merkle inclusion proof of a credential hash against an anchored root
+	#[rpc(name = "did_verifyBatchInclusion")]
+	fn verify_batch_inclusion(
+		&self,
+		merkle_root: Bytes,
+		vc_hash: Bytes,
+		proof: Vec<Bytes>,
+		at: Option<BlockHash>,
+	) -> Result<Option<VerifiedBatch<AccountId>>>;
+
+	/// Lists the DID URIs controlled by an account
+	#[rpc(name = "did_listByController")]
+	fn dids_by_controller(
+		&self,
+		controller: AccountId,
+		at: Option<BlockHash>,
+	) -> Result<Vec<Bytes>>;
+
+	/// Lists the DID URIs signed by a public key
+	#[rpc(name = "did_listByPublicKey")]
+	fn dids_by_public_key(&self, public_key: Bytes, at: Option<BlockHash>) -> Result<Vec<Bytes>>;
+
+	/// Resolves a DID, its document is read from the offchain DB of nodes indexing it or fetched
+	/// from the IPFS gateway when one is configured
+	#[rpc(name = "did_resolve")]
+	fn resolve(
+		&self,
+		did_uri: Bytes,
+		at: Option<BlockHash>,
+	) -> BoxFuture<Option<ResolvedDID<AccountId>>>;
+
+	/// Checks whether a DID is stored and not revoked
+	#[rpc(name = "did_isActive")]
+	fn is_active(&self, did_uri: Bytes, at: Option<BlockHash>) -> Result<bool>;
+
+	/// Checks whether a message is signed by an active key of an active DID
+	#[rpc(name = "did_verify")]
+	fn verify(
+		&self,
+		did_uri: Bytes,
+		public_key: Bytes,
+		message: Bytes,
+		signature: Bytes,
+		at: Option<BlockHash>,
+	) -> Result<bool>;
+
+	/// Returns the status of a verifiable credential hash, with its issuer's DID and accreditation
+	#[rpc(name = "did_vcStatus")]
+	fn vc_status(&self, vc_hash: Bytes, at: Option<BlockHash>) -> Result<Option<CredentialStatus>>;
+}
+
+/// Implements the DID RPC methods
+pub struct DID<C, B, S> {
+	client: Arc<C>,
+	offchain_storage: Option<S>,
+	ipfs_gateway: Option<String>,
+	fetch_pool: ThreadPool,
+	_marker: PhantomData<B>,
+}
+
+/// Number of threads fetching DID documents from the IPFS gateway
+const FETCH_THREADS: usize = 2;
+
+impl<C, B, S: OffchainStorage> DID<C, B, S> {
+	/// Creates a new instance of the DID RPC handler
+	/// DID documents are read from `offchain_storage` when the node runs with offchain indexing,
+	/// otherwise fetched from `ipfs_gateway`, e.g. `http://127.0.0.1:8080`, by a small pool of
+	/// threads so RPC threads do not wait for the gateway
+	pub fn new(client: Arc<C>, offchain_storage: Option<S>, ipfs_gateway: Option<String>) -> Self {
+		let fetch_pool = ThreadPool::builder()
+			.pool_size(FETCH_THREADS)
+			.name_prefix("did-resolve-")
+			.create()
+			.expect("Threads fetching DID documents can be created; qed");
+
+		Self { client, offchain_storage, ipfs_gateway, fetch_pool, _marker: Default::default() }
+	}
+
+	/// Reads a DID document version indexed off chain
+	fn indexed_document(&self, did_uri: &[u8], version: u32) -> Option<Vec<u8>> {
+		let key = document_key(did_uri, version);
+		self.offchain_storage.as_ref()?.get(sp_offchain::STORAGE_PREFIX, &key)
+	}
+
+	/// Fetches a DID document on the fetch pool and checks it against the anchored hash
+	fn fetch_document(
+		&self,
+		did_ref: Vec<u8>,
+		did_document_hash: Vec<u8>,
+	) -> impl std::future::Future<Output = Result<Option<Vec<u8>>>> {
+		let fetched = self.ipfs_gateway.clone().map(|gateway| {
+			self.fetch_pool
+				.spawn_with_handle(async move { ipfs::fetch(&gateway, &did_ref) })
+		});
+
+		async move {
+			let document = match fetched {
+				| Some(Ok(fetched)) => fetched.await,
+				| Some(Err(e)) => Err(e.to_string()),
+				| None => return Ok(None),
+			}
+			.map_err(|e| RpcError {
+				code: ErrorCode::ServerError(INVALID_DOCUMENT),
+				message: "Unable to fetch the DID document.".into(),
+				data: Some(e.into()),
+			})?;
+
+			if blake2_256(&document)[..] != did_document_hash[..] {
+				return Err(RpcError {
+					code: ErrorCode::ServerError(INVALID_DOCUMENT),
+					message: "DID document does not match the anchored hash.".into(),
+					data: None,
+				})
+			}
+
+			Ok(Some(document))
+		}
+	}
+}
+
+impl<C, Block, AccountId, S> DIDApi<<Block as BlockT>::Hash, AccountId> for DID<C, Block, S>
+where
+	Block: BlockT,
+	S: OffchainStorage + 'static,
+	C: Send + Sync + 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: DIDRuntimeApi<Block, AccountId>,
+	AccountId: Codec + Send + 'static,
+{
+	fn verify_credential(
+		&self,
+		credential: String,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Option<VerifiedCredential<AccountId>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		let vc_hash = credential::credential_hash(&credential).map_err(|e| RpcError {
+			code: ErrorCode::ServerError(INVALID_CREDENTIAL),
+			message: "Unable to canonicalise the credential.".into(),
+			data: Some(e.to_string().into()),
+		})?;
+
+		let fingerprint = api.vc_fingerprint(&at, vc_hash.to_vec()).map_err(runtime_error)?;
+
+		Ok(fingerprint.map(|vc| VerifiedCredential {
+			vc_hash: vc_hash.to_vec().into(),
+			issuer: vc.issuer,
+			holder_public_key: vc.holder_public_key.into(),
+			active: vc.active,
+			block_time_stamp: vc.block_time_stamp,
+			schema_id: vc.schema_id.map(Into::into),
+			issuer_did: vc.issuer_did.map(Into::into),
+			credential_type: vc.credential_type.map(Into::into),
+		}))
+	}
+
+	fn trusted_issuers(
+		&self,
+		credential_type: Bytes,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<Bytes>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.trusted_issuers(&at, credential_type.to_vec())
+			.map(|issuers| issuers.into_iter().map(Into::into).collect())
+			.map_err(runtime_error)
+	}
+
+	fn verify_batch_inclusion(
+		&self,
+		merkle_root: Bytes,
+		vc_hash: Bytes,
+		proof: Vec<Bytes>,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Option<VerifiedBatch<AccountId>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		let batch = api
+			.verify_batch_inclusion(
+				&at,
+				merkle_root.to_vec(),
+				vc_hash.to_vec(),
+				proof.into_iter().map(|p| p.to_vec()).collect(),
+			)
+			.map_err(runtime_error)?;
+
+		Ok(batch.map(|batch| VerifiedBatch {
+			merkle_root,
+			issuer: batch.issuer,
+			issuer_did: batch.issuer_did.map(Into::into),
+			credential_type: batch.credential_type.map(Into::into),
+			leaf_count: batch.leaf_count,
+			block_time_stamp: batch.block_time_stamp,
+		}))
+	}
+
+	fn dids_by_controller(
+		&self,
+		controller: AccountId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<Bytes>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.dids_by_controller(&at, controller)
+			.map(|dids| dids.into_iter().map(Into::into).collect())
+			.map_err(runtime_error)
+	}
+
+	fn dids_by_public_key(
+		&self,
+		public_key: Bytes,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<Bytes>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.dids_by_public_key(&at, public_key.to_vec())
+			.map(|dids| dids.into_iter().map(Into::into).collect())
+			.map_err(runtime_error)
+	}
+
+	fn resolve(
+		&self,
+		did_uri: Bytes,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> BoxFuture<Option<ResolvedDID<AccountId>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		let did = match api.resolve(&at, did_uri.to_vec()).map_err(runtime_error) {
+			| Ok(Some(did)) => did,
+			| resolved => {
+				let resolved: Result<Option<ResolvedDID<AccountId>>> = resolved.map(|_| None);
+				return Box::new(async move { resolved }.boxed().compat())
+			},
+		};
+
+		let document = match (self.indexed_document(&did_uri, did.version), &did.did_ref) {
+			| (Some(document), _) if blake2_256(&document)[..] == did.did_document_hash[..] =>
+				async move { Ok::<_, RpcError>(Some(document)) }.boxed(),
+			| (_, Some(did_ref)) =>
+				self.fetch_document(did_ref.clone(), did.did_document_hash.clone()).boxed(),
+			| _ => async { Ok::<_, RpcError>(None) }.boxed(),
+		};
+
+		let resolved = async move {
+			let document = document.await?;
+
+			Ok::<_, RpcError>(Some(ResolvedDID {
+				did_uri,
+				controller: did.controller,
+				did_document: document.map(|document| {
+					serde_json::from_slice(&document).unwrap_or_else(|_| {
+						serde_json::Value::String(String::from_utf8_lossy(&document).into())
+					})
+				}),
+				did_document_hash: did.did_document_hash.into(),
+				did_document_metadata: did.did_document_metadata.map(Into::into),
+				did_resolution_metadata: did.did_resolution_metadata.map(Into::into),
+				did_ref: did.did_ref.map(Into::into),
+				public_keys: did.public_keys.into_iter().map(Into::into).collect(),
+				version: did.version,
+				block_time_stamp: did.block_time_stamp,
+				updated_time_stamp: did.updated_time_stamp,
+			}))
+		};
+
+		Box::new(resolved.boxed().compat())
+	}
+
+	fn is_active(&self, did_uri: Bytes, at: Option<<Block as BlockT>::Hash>) -> Result<bool> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.is_active(&at, did_uri.to_vec()).map_err(runtime_error)
+	}
+
+	fn verify(
+		&self,
+		did_uri: Bytes,
+		public_key: Bytes,
+		message: Bytes,
+		signature: Bytes,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<bool> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.verify(&at, did_uri.to_vec(), public_key.to_vec(), message.to_vec(), signature.to_vec())
+			.map_err(runtime_error)
+	}
+
+	fn vc_status(
+		&self,
+		vc_hash: Bytes,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Option<CredentialStatus>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.vc_status(&at, vc_hash.to_vec()).map_err(runtime_error)
+	}
+}
+
+/// Maps a runtime API failure to an RPC error
+fn runtime_error(e: impl std::fmt::Debug) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(RUNTIME_ERROR),
+		message: "Unable to query the DID runtime API.".into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
@@ -0,0 +1,120 @@
+// This file is part of TrackBck which is released under GNU General Public License v3.0.
+// See file LICENSE.md or go to https://www.gnu.org/licenses/gpl-3.0.en.html for full license details.
+//! Fee payment delegation
+//! * `sponsor_fees` lets an account, e.g. an issuer DID's controller, pay the fees of another
+//!   account's DID operations up to an allowance
+//! * `SponsoredCurrencyAdapter` charges the sponsor for `insert_did_document`, `update_did` and
+//!   `revoke_did` when the allowance covers the fee and the sponsor can pay it, the sender pays
+//!   otherwise
+//! * Calls free of fees, e.g. DID operations with `FeelessDIDOperations`, never draw on the
+//!   allowance, the sender pays their tip
+//! * Refunds of unused weight go back to the payer, and to the allowance when it was drawn
+
+use crate::pallet::{BalanceOf, Call, Config, Pallet};
+use frame_support::{
+	traits::{Currency, ExistenceRequirement, Imbalance, IsSubType, OnUnbalanced, WithdrawReasons},
+	weights::Pays,
+};
+use pallet_transaction_payment::OnChargeTransaction;
+use sp_runtime::{
+	traits::{DispatchInfoOf, PostDispatchInfoOf, Saturating, Zero},
+	transaction_validity::{InvalidTransaction, TransactionValidityError},
+};
+use sp_std::marker::PhantomData;
+
+type PositiveImbalanceOf<T> = <<T as Config>::Currency as Currency<
+	<T as frame_system::Config>::AccountId,
+>>::PositiveImbalance;
+type NegativeImbalanceOf<T> = <<T as Config>::Currency as Currency<
+	<T as frame_system::Config>::AccountId,
+>>::NegativeImbalance;
+
+/// `pallet_transaction_payment::CurrencyAdapter` paying DID operations from the sender's fee
+/// sponsor, fees and tips are handed to `OU`
+pub struct SponsoredCurrencyAdapter<OU>(PhantomData<OU>);
+
+impl<T, OU> OnChargeTransaction<T> for SponsoredCurrencyAdapter<OU>
+where
+	T: Config + pallet_transaction_payment::Config,
+	<T as frame_system::Config>::Call: IsSubType<Call<T>>,
+	PositiveImbalanceOf<T>: Imbalance<BalanceOf<T>, Opposite = NegativeImbalanceOf<T>>,
+	NegativeImbalanceOf<T>: Imbalance<BalanceOf<T>, Opposite = PositiveImbalanceOf<T>>,
+	OU: OnUnbalanced<NegativeImbalanceOf<T>>,
+{
+	type Balance = BalanceOf<T>;
+	type LiquidityInfo = Option<(T::AccountId, NegativeImbalanceOf<T>)>;
+
+	fn withdraw_fee(
+		who: &T::AccountId,
+		call: &<T as frame_system::Config>::Call,
+		info: &DispatchInfoOf<<T as frame_system::Config>::Call>,
+		fee: Self::Balance,
+		tip: Self::Balance,
+	) -> Result<Self::LiquidityInfo, TransactionValidityError> {
+		if fee.is_zero() {
+			return Ok(None)
+		}
+
+		let withdraw_reason = if tip.is_zero() {
+			WithdrawReasons::TRANSACTION_PAYMENT
+		} else {
+			WithdrawReasons::TRANSACTION_PAYMENT | WithdrawReasons::TIP
+		};
+		let withdraw = |payer: &T::AccountId| {
+			T::Currency::withdraw(payer, fee, withdraw_reason, ExistenceRequirement::KeepAlive)
+		};
+
+		if info.pays_fee == Pays::Yes && is_sponsored::<T>(call) {
+			let sponsor = Pallet::<T>::draw_fee_allowance(who, fee);
+			if &sponsor != who {
+				match withdraw(&sponsor) {
+					| Ok(imbalance) => return Ok(Some((sponsor, imbalance))),
+					| Err(_) => Pallet::<T>::refund_fee_allowance(who, &sponsor, fee),
+				}
+			}
+		}
+
+		match withdraw(who) {
+			| Ok(imbalance) => Ok(Some((who.clone(), imbalance))),
+			| Err(_) => Err(InvalidTransaction::Payment.into()),
+		}
+	}
+
+	fn correct_and_deposit_fee(
+		who: &T::AccountId,
+		_dispatch_info: &DispatchInfoOf<<T as frame_system::Config>::Call>,
+		_post_info: &PostDispatchInfoOf<<T as frame_system::Config>::Call>,
+		corrected_fee: Self::Balance,
+		tip: Self::Balance,
+		already_withdrawn: Self::LiquidityInfo,
+	) -> Result<(), TransactionValidityError> {
+		if let Some((payer, paid)) = already_withdrawn {
+			let refund_amount = paid.peek().saturating_sub(corrected_fee);
+			let refund_imbalance = T::Currency::deposit_into_existing(&payer, refund_amount)
+				.unwrap_or_else(|_| PositiveImbalanceOf::<T>::zero());
+			if &payer != who {
+				Pallet::<T>::refund_fee_allowance(who, &payer, refund_imbalance.peek());
+			}
+
+			let adjusted_paid = paid
+				.offset(refund_imbalance)
+				.map_err(|_| TransactionValidityError::Invalid(InvalidTransaction::Payment))?;
+			let (tip, fee) = adjusted_paid.split(tip);
+			OU::on_unbalanceds(Some(fee).into_iter().chain(Some(tip)));
+		}
+		Ok(())
+	}
+}
+
+/// Whether the call is a DID operation whose fee a sponsor pays
+fn is_sponsored<T: Config>(call: &<T as frame_system::Config>::Call) -> bool
+where
+	<T as frame_system::Config>::Call: IsSubType<Call<T>>,
+{
+	matches!(
+		call.is_sub_type(),
+		Some(Call::insert_did_document(..)) |
+			Some(Call::update_did(..)) |
+			Some(Call::revoke_did(..))
+	)
+}
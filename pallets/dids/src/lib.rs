@@ -3,10 +3,19 @@
 //! TrackBack limited
 //! Decentralised Identifiers Pallet Implementation TrackBack Limited
 //! * Creates a decentralised identifier
+//! * Lets holders without an account register their DID with an unsigned extrinsic
 //! * Revokes a decentralised identifier
 //! * Checks an existence of a decentralised identifier
 //! * Creates a finger print of a verifiable credential
 //! * Checks an existence of a verifiable credential
+//! * Registers JSON Schemas verifiable credentials conform to
+//! * Keeps a registry of accredited issuers per credential type
+//! * Anchors receipts of credentials presented by holders to verifiers
+//! * Anchors Merkle roots over batches of verifiable credential hashes
+//! * Flags DIDs whose referenced document does not match the anchored hash (offchain worker)
+//! * Dispatches calls with the origin of a DID, authorised by one of its keys (see `origin`)
+//! * Lets sponsors pay the fees of other accounts' DID operations (see `fees`)
+//! * Rate limits DID operations, which may be made free of fees (see `limits`)
 //!
 //! # Storage
 //! ## DIDDocument
@@ -25,21 +34,19 @@
 //! pub(super) type DIDDocument<T: Config> = StorageMap<_, Blake2_128Concat, Vec<u8>, DID<T>>;
 //! ```
 //!
-//! ## DIDDocument
-//! Keeps trails of DID documents by combination of the  Issuer/Controller Account and a unique
-//! value
-//! * Key 1 -> AccountId + DIDDocumentHash
-//! * Value -> DID structure
+//! ## DIDsByController / DIDsByPublicKey
+//! Indexes DID URIs by their Issuer/Controller Account and by the public keys signing them
+//! * Key 1 -> Controller's AccountId or public key
+//! * Key 2 -> DID URI
 //!
 //! ```no_run
 //! use frame_support::pallet;
-//! use frame_support::pallet_prelude::StorageMap;
+//! use frame_support::pallet_prelude::StorageDoubleMap;
 //! use frame_support::Blake2_128Concat;
 //! use pallet_dids::Config;
 //! #[pallet::storage]
-//! #[pallet::getter(fn get_did_accounts)]
-//! pub(super) type DIDs<T: Config> =
-//!     StorageMap<_, Blake2_128Concat, (Vec<u8>, T::AccountId), Vec<DID<T>>>;
+//! pub type DIDsByController<T: Config> =
+//!     StorageDoubleMap<_, Blake2_128Concat, T::AccountId, Blake2_128Concat, Vec<u8>, ()>;
 //! ```
 //! ## VerifiableCredential
 //! * Stores a fingerprint of a verifiableCredential
@@ -54,11 +61,61 @@
 //! pub(super) type VC<T: Config> =
 //!     StorageMap<_, Blake2_128Concat, Vec<u8>, VerifiableCredential<T>>;
 //! ```
+//! ## Schemas
+//! * Stores JSON Schemas published by issuers, keyed by the schema hash
+//! * A verifiable credential fingerprint can reference a registered schema by its hash
+//!
+//! ## TrustedIssuers
+//! * Issuer DIDs accredited per credential type by `AccreditationOrigin`
+//! * When `AccreditationRequired` is set, `create_vc_fingerprint` only accepts accredited issuers
+//!
+//! ## Presentations
+//! * Receipts of a holder presenting a credential to a verifier, keyed by the presentation hash
+//! * Holds the credential hash, both DID URIs and the holder's consent signatures only
+//!
+//! ## VCBatches
+//! * Merkle roots over many verifiable credential hashes anchored in a single extrinsic
+//! * See `utils::merkle` for the tree layout issuers must use
+//!
+//! ## DIDRefMismatches
+//! * DIDs whose document fetched from `did_ref` does not hash to `did_document_hash`
+//! * Reported unsigned by offchain workers of authorities, see `offchain` for the gateway
+//!   configuration
+//! * Cleared when the DID is updated or revoked
+//!
+//! ## DIDNonces
+//! * Nonce of the next call a DID authorises with `submit_did_call` or
+//!   `insert_did_document_unsigned`
+//! * Kept when the DID is revoked, so its signed calls can not be replayed
+//!
+//! ## FeeSponsors
+//! * Account paying the fees of another account's DID operations and its remaining allowance
+//! * Drawn by `fees::SponsoredCurrencyAdapter`, the runtime's `OnChargeTransaction`
+//!
+//! ## DIDOperations / AccountOperations
+//! * Rate limit era and number of DID operations made by a DID and by an account in it
+//! * Counted by `limits::CheckDidOperationLimits` before dispatch
+//!
+//! ## KeyOperations / UnsignedDIDRegistrations
+//! * DIDs registered without an account per signing key and era, and per block
+//! * Counted by `insert_did_document_unsigned`, checked before it enters the pool
+//!
+//! ## PalletStorageVersion
+//! * Storage layout, migrated on runtime upgrade by `migrations`
+//!
+//! # Offchain indexing
+//! * Full DID documents are written to the offchain DB keyed by `offchain::document_key`
+//! * Nodes must run with `--enable-offchain-indexing true` to keep them
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
-mod structs;
-mod utils;
+pub mod fees;
+pub mod limits;
+pub mod migrations;
+pub mod offchain;
+pub mod origin;
+pub mod structs;
+pub mod utils;
 
 #[cfg(test)]
 mod mock;
@@ -66,35 +123,134 @@ mod mock;
 #[cfg(test)]
 mod tests;
 
+pub use fees::SponsoredCurrencyAdapter;
+pub use limits::CheckDidOperationLimits;
+pub use origin::{ensure_did, CheckDidAuthorization, EnsureDid, RawOrigin};
 pub use pallet::*;
 
 #[frame_support::pallet]
 pub mod pallet {
 
-	use frame_support::{dispatch::DispatchResultWithPostInfo, pallet_prelude::*};
+	use frame_support::{
+		dispatch::DispatchResultWithPostInfo,
+		pallet_prelude::*,
+		weights::{GetDispatchInfo, PostDispatchInfo},
+	};
 
 	use frame_system::pallet_prelude::*;
 
-	use crate::structs::{DIDSignature, VerifiableCredential, DID};
+	use crate::{
+		migrations, offchain,
+		origin::{did_nonce_tag, RawOrigin},
+		structs::{
+			CredentialSchema, DIDRefReport, DIDSignature, GenesisDID, PresentationReceipt,
+			Releases, VerifiableCredential, VerifiableCredentialBatch, VerifiableCredentialStatus,
+			DID,
+		},
+		utils::{cid::Cid, merkle},
+	};
+	use frame_support::traits::Currency;
 	#[allow(dead_code)]
 	use frame_support::traits::UnixTime;
+	use frame_system::offchain::{SendTransactionTypes, SubmitTransaction};
 	use sp_core::ed25519;
-	use sp_runtime::sp_std::convert::TryFrom;
-	use sp_std::{str, vec::Vec};
+	use sp_io::hashing::blake2_256;
+	use sp_runtime::{
+		sp_std::convert::TryFrom,
+		traits::{Dispatchable, Saturating, UniqueSaturatedInto, Zero},
+		transaction_validity::{
+			InvalidTransaction, TransactionLongevity, TransactionPriority, TransactionSource,
+			TransactionValidity, ValidTransaction,
+		},
+		RuntimeAppPublic,
+	};
+	use sp_std::{boxed::Box, str, vec::Vec};
 
 	use frame_support::sp_runtime::app_crypto::RuntimePublic;
 	use sp_core::ed25519::Signature as Proof;
 
 	#[pallet::config]
-	pub trait Config: frame_system::Config + pallet_timestamp::Config {
+	pub trait Config:
+		SendTransactionTypes<Call<Self>> + frame_system::Config + pallet_timestamp::Config
+	{
 		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
 		type TimeProvider: UnixTime;
+
+		/// Currency fees of sponsored DID operations are paid in
+		type Currency: Currency<Self::AccountId>;
+
+		/// Outer origin, calls authorised by a DID are dispatched with `RawOrigin::DID`
+		type Origin: From<RawOrigin>;
+
+		/// Outer call, dispatched by `submit_did_call`
+		type Call: Parameter
+			+ Dispatchable<Origin = <Self as Config>::Origin, PostInfo = PostDispatchInfo>
+			+ GetDispatchInfo;
+
+		/// Origin which accredits issuers, e.g. sudo or a council
+		type AccreditationOrigin: EnsureOrigin<<Self as frame_system::Config>::Origin>;
+
+		/// Origin registering credential schemas as their author DID, succeeding with its URI,
+		/// e.g. `EnsureDid`
+		type SchemaOrigin: EnsureOrigin<<Self as frame_system::Config>::Origin, Success = Vec<u8>>;
+
+		/// Key offchain workers sign DID reference mismatch reports with
+		type AuthorityId: Member + Parameter + RuntimeAppPublic;
+
+		/// Authorities whose DID reference mismatch reports are accepted
+		type Authorities: Get<Vec<Self::AuthorityId>>;
+
+		/// Priority of unsigned transactions reporting DID reference mismatches
+		#[pallet::constant]
+		type UnsignedPriority: Get<TransactionPriority>;
+
+		/// Priority of unsigned DID registrations
+		#[pallet::constant]
+		type UnsignedDIDPriority: Get<TransactionPriority>;
+
+		/// Number of blocks unsigned DID registrations stay valid in the pool
+		#[pallet::constant]
+		type UnsignedDIDLongevity: Get<TransactionLongevity>;
+
+		/// Number of blocks between offchain checks of DID references, zero disables them
+		#[pallet::constant]
+		type DIDRefCheckInterval: Get<Self::BlockNumber>;
+
+		/// Whether `insert_did_document` and `update_did` are free of fees, their proofs are
+		/// then checked before they enter the pool, see `limits`
+		#[pallet::constant]
+		type FeelessDIDOperations: Get<bool>;
+
+		/// Number of blocks of a DID operation rate limit era, zero disables the limits
+		#[pallet::constant]
+		type DIDOperationEra: Get<Self::BlockNumber>;
+
+		/// Number of DID operations a DID and an account may make per era
+		#[pallet::constant]
+		type MaxDIDOperationsPerEra: Get<u32>;
+
+		/// Largest encoded `insert_did_document_unsigned` call, bounding the document with its
+		/// metadata and proofs
+		#[pallet::constant]
+		type MaxUnsignedDIDSize: Get<u32>;
+
+		/// Number of DIDs registered without an account per block, bounding the storage they
+		/// take without fees whatever keys and DID URIs they are registered with
+		#[pallet::constant]
+		type MaxUnsignedDIDsPerBlock: Get<u32>;
 	}
 
+	pub type BalanceOf<T> =
+		<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+
 	#[pallet::pallet]
 	#[pallet::generate_store(pub(super) trait Store)]
 	pub struct Pallet<T>(_);
 
+	/// Origin of calls authorised by a DID, see `origin`
+	#[pallet::origin]
+	pub type Origin = RawOrigin;
+
 	/// Stores a DID document on chain
 	/// Key 1 -> AccountId + DIDDocumentHash
 	/// Value -> DID structure
@@ -109,81 +265,282 @@ pub mod pallet {
 	pub(super) type DIDProof<T: Config> =
 		StorageMap<_, Blake2_128Concat, Vec<u8>, Vec<DIDSignature>>;
 
-	/// Accounts associated with a DID
+	/// Controller Account of a DID
+	/// Key 1 -> DID URI
+	/// Value -> Controller's AccountId
+	#[pallet::storage]
+	#[pallet::getter(fn get_did_controller)]
+	pub type DIDController<T: Config> = StorageMap<_, Blake2_128Concat, Vec<u8>, T::AccountId>;
+
+	/// DIDs controlled by an Account
+	/// Key 1 -> Controller's AccountId
+	/// Key 2 -> DID URI
+	#[pallet::storage]
+	pub type DIDsByController<T: Config> =
+		StorageDoubleMap<_, Blake2_128Concat, T::AccountId, Blake2_128Concat, Vec<u8>, ()>;
+
+	/// DIDs signed by a public key
+	/// Key 1 -> Public key
+	/// Key 2 -> DID URI
 	#[pallet::storage]
-	#[pallet::getter(fn get_did_accounts)]
-	pub(super) type DIDs<T: Config> = StorageMap<
-		_,
-		Blake2_128Concat,
-		// public key + Controller Account
-		(Vec<u8>, T::AccountId),
-		Vec<DID>,
-	>;
+	pub type DIDsByPublicKey<T: Config> =
+		StorageDoubleMap<_, Blake2_128Concat, Vec<u8>, Blake2_128Concat, Vec<u8>, ()>;
 
 	/// Stores a verifiable credential finger print
 	#[pallet::storage]
 	#[pallet::getter(fn get_verifiable_credential_hash)]
 	pub type VC<T: Config> = StorageMap<_, Blake2_128Concat, Vec<u8>, VerifiableCredential<T>>;
 
+	/// Stores JSON Schemas of verifiable credentials
+	/// Key 1 -> Schema hash
+	/// Value -> Schema author, version and off-chain reference
+	#[pallet::storage]
+	#[pallet::getter(fn get_schema)]
+	pub type Schemas<T: Config> = StorageMap<_, Blake2_128Concat, Vec<u8>, CredentialSchema>;
+
+	/// Accredited issuers per credential type
+	/// Key 1 -> Credential type
+	/// Key 2 -> Issuer's DID URI
+	/// Value -> Accredited time
+	#[pallet::storage]
+	#[pallet::getter(fn get_trusted_issuer)]
+	pub type TrustedIssuers<T: Config> =
+		StorageDoubleMap<_, Blake2_128Concat, Vec<u8>, Blake2_128Concat, Vec<u8>, u64>;
+
+	/// Credential types an issuer is accredited for
+	/// Key 1 -> Issuer's DID URI
+	/// Key 2 -> Credential type
+	#[pallet::storage]
+	pub type IssuerAccreditations<T: Config> =
+		StorageDoubleMap<_, Blake2_128Concat, Vec<u8>, Blake2_128Concat, Vec<u8>, ()>;
+
+	/// Storage layout of the pallet, migrations run on runtime upgrade until it is current
+	#[pallet::storage]
+	#[pallet::getter(fn pallet_storage_version)]
+	pub type PalletStorageVersion<T: Config> = StorageValue<_, Releases, ValueQuery>;
+
+	/// Whether verifiable credential fingerprints can only be anchored by accredited issuers
+	#[pallet::storage]
+	#[pallet::getter(fn accreditation_required)]
+	pub type AccreditationRequired<T: Config> = StorageValue<_, bool, ValueQuery>;
+
+	/// Receipts of verifiable credentials presented to verifiers
+	/// Key 1 -> Presentation hash
+	/// Value -> Presentation receipt
+	#[pallet::storage]
+	#[pallet::getter(fn get_presentation)]
+	pub type Presentations<T: Config> =
+		StorageMap<_, Blake2_128Concat, Vec<u8>, PresentationReceipt>;
+
+	/// Merkle roots over batches of verifiable credential hashes
+	/// Key 1 -> Merkle root
+	/// Value -> Batch issuer and size
+	#[pallet::storage]
+	#[pallet::getter(fn get_vc_batch)]
+	pub type VCBatches<T: Config> =
+		StorageMap<_, Blake2_128Concat, Vec<u8>, VerifiableCredentialBatch<T>>;
+
+	/// DIDs whose document referenced by `did_ref` does not match the anchored document hash
+	/// Key 1 -> DID URI
+	/// Value -> Hash of the fetched document
+	#[pallet::storage]
+	#[pallet::getter(fn get_did_ref_mismatch)]
+	pub type DIDRefMismatches<T: Config> = StorageMap<_, Blake2_128Concat, Vec<u8>, Vec<u8>>;
+
+	/// Nonce of the next call authorised by a DID
+	/// Key 1 -> DID URI
+	/// Value -> Nonce
+	#[pallet::storage]
+	#[pallet::getter(fn did_nonce)]
+	pub type DIDNonces<T: Config> = StorageMap<_, Blake2_128Concat, Vec<u8>, u64, ValueQuery>;
+
+	/// Sponsor paying the fees of an account's DID operations
+	/// Key 1 -> Beneficiary's AccountId
+	/// Value -> Sponsor's AccountId, remaining allowance
+	#[pallet::storage]
+	#[pallet::getter(fn fee_sponsor)]
+	pub type FeeSponsors<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, (T::AccountId, BalanceOf<T>)>;
+
+	/// DID operations made by a DID
+	/// Key 1 -> DID URI
+	/// Value -> Rate limit era, number of operations in it
+	#[pallet::storage]
+	pub type DIDOperations<T: Config> =
+		StorageMap<_, Blake2_128Concat, Vec<u8>, (T::BlockNumber, u32), ValueQuery>;
+
+	/// DID operations made by an account
+	/// Key 1 -> Sender's AccountId
+	/// Value -> Rate limit era, number of operations in it
+	#[pallet::storage]
+	pub type AccountOperations<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, (T::BlockNumber, u32), ValueQuery>;
+
+	/// DIDs registered without an account by a public key
+	/// Key 1 -> Public key signing the registration
+	/// Value -> Rate limit era, number of registrations in it
+	#[pallet::storage]
+	pub type KeyOperations<T: Config> =
+		StorageMap<_, Blake2_128Concat, Vec<u8>, (T::BlockNumber, u32), ValueQuery>;
+
+	/// DIDs registered without an account in a block
+	/// Value -> Block number, number of registrations in it
+	#[pallet::storage]
+	pub type UnsignedDIDRegistrations<T: Config> =
+		StorageValue<_, (T::BlockNumber, u32), ValueQuery>;
+
+	/// DIDs and verifiable credential fingerprints seeded at genesis
+	/// * DID proofs and references are verified against the documents, which are not kept
+	/// * Entries with an empty DID URI or credential hash are skipped
 	#[pallet::genesis_config]
 	pub struct GenesisConfig<T: Config> {
-		pub did: (Vec<u8>, DID),
-		pub vc: (Vec<u8>, VerifiableCredential<T>),
+		pub dids: Vec<GenesisDID<T::AccountId>>,
+		pub vcs: Vec<(Vec<u8>, VerifiableCredential<T>)>,
 	}
 
 	#[cfg(feature = "std")]
 	impl<T: Config> Default for GenesisConfig<T> {
 		fn default() -> Self {
-			Self {
-				did: (
-					vec![],
-					DID {
-						did_resolution_metadata: None,
-						did_document_metadata: None,
-						block_time_stamp: 0,
-						updated_time_stamp: 0,
-						did_ref: None,
-						sender_account_id: vec![],
-					},
-				),
-				vc: Default::default(),
-			}
+			Self { dids: vec![], vcs: vec![] }
 		}
 	}
 
 	#[pallet::genesis_build]
 	impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
 		fn build(&self) {
-			let (a, b) = &self.vc;
-			let (x, y) = &self.did;
-			<DIDDocument<T>>::insert(x, y);
-			<VC<T>>::insert(a, b);
+			for did in self.dids.iter().filter(|did| !did.did_uri.is_empty()) {
+				assert!(
+					!DIDDocument::<T>::contains_key(&did.did_uri),
+					"Genesis DID URIs must be unique"
+				);
+				assert!(
+					did.signatures.iter().all(|signature| {
+						Pallet::<T>::verify_signature(
+							&signature.public_key,
+							&signature.proof,
+							&did.did_document,
+						)
+					}),
+					"Genesis DID proofs must verify against their documents"
+				);
+				assert!(
+					Pallet::<T>::ensure_did_ref(&did.did_ref, &did.did_document).is_ok(),
+					"Genesis DID references must address their documents"
+				);
+
+				Pallet::<T>::index_public_keys(&did.did_uri, &did.signatures);
+				DIDProof::<T>::insert(&did.did_uri, &did.signatures);
+				DIDController::<T>::insert(&did.did_uri, &did.controller);
+				DIDsByController::<T>::insert(&did.controller, &did.did_uri, ());
+				DIDDocument::<T>::insert(
+					&did.did_uri,
+					DID {
+						did_resolution_metadata: did.did_resolution_metadata.clone(),
+						did_document_metadata: did.did_document_metadata.clone(),
+						block_time_stamp: 0,
+						updated_time_stamp: 0,
+						did_ref: did.did_ref.clone(),
+						sender_account_id: did.controller.encode(),
+						did_document_hash: blake2_256(&did.did_document).to_vec(),
+						version: 1,
+					},
+				);
+			}
+
+			for (vc_hash, vc) in self.vcs.iter().filter(|(vc_hash, _)| !vc_hash.is_empty()) {
+				VC::<T>::insert(vc_hash, vc);
+			}
+
+			PalletStorageVersion::<T>::put(Releases::V2_0_0);
 		}
 	}
 
 	/// # Pallet Events
-	/// * DIDDocumentCreated
-	/// - Returns the created    DID hash and the AccountId `(Vec<u8>, T::AccountId)`
+	/// * DIDDocumentCreated / DIDDocumentUpdated
+	/// - Returns the DID URI, the sender's Account, the version, the document hash, the signer keys
+	///   and the time stamp, enough to rebuild a DID from events only
 	/// * DIDDocumentRevoked
-	/// - Triggers when a DID revoked by a controller or a delegated authority `(Vec<u8>,
-	///   T::AccountId)`
+	/// - Triggers when a DID revoked by a controller or a delegated authority, with the sender's
+	///   Account, the reason given and the time stamp
+	/// * DIDKeyAdded / DIDKeyRemoved
+	/// - Returns the DID URI and the public key signing, or no longer signing, its document
 	/// * VerifiableCredentialFingerprintCreated
 	/// - Returns Holder's Account, Issuer/Controller's Account and the verifiable credential hash
+	/// * SchemaRegistered
+	/// - Returns the schema hash, the author's DID URI and the sender's Account
+	/// * IssuerAccredited / IssuerAccreditationRevoked
+	/// - Returns the credential type and the issuer's DID URI
+	/// * AccreditationRequirementSet
+	/// - Returns whether accreditation is required to anchor fingerprints
+	/// * PresentationAnchored
+	/// - Returns the presentation hash, verifier's and holder's DID URIs and the sender's Account
+	/// * VerifiableCredentialBatchAnchored
+	/// - Returns the Merkle root, the sender's Account and the number of credential hashes
 	#[pallet::event]
-	#[pallet::metadata(T::AccountId = "AccountId")]
+	#[pallet::metadata(T::AccountId = "AccountId", BalanceOf<T> = "Balance")]
 	#[pallet::generate_deposit(pub(super) fn deposit_event)]
 	pub enum Event<T: Config> {
-		/// Event returns DID Document hash, DID URI, Sender's AccountId
-		DIDDocumentCreated(Vec<u8>, T::AccountId),
+		/// DID Document created
+		/// DID URI, Sender's AccountId, version, document hash, signer keys, time stamp
+		DIDDocumentCreated(Vec<u8>, T::AccountId, u32, Vec<u8>, Vec<Vec<u8>>, u64),
 
 		/// DID Document revoked
-		DIDDocumentRevoked(Vec<u8>, T::AccountId),
+		/// DID URI, Sender's AccountId, reason, time stamp
+		DIDDocumentRevoked(Vec<u8>, T::AccountId, Option<Vec<u8>>, u64),
 
 		/// Verifiable credential fingerprint created
 		VerifiableCredentialFingerPrintCreated(Vec<u8>, T::AccountId, Vec<u8>),
 
 		/// DID Document updated
-		DIDDocumentUpdated(Vec<u8>),
+		/// DID URI, Sender's AccountId, version, document hash, signer keys, time stamp
+		DIDDocumentUpdated(Vec<u8>, T::AccountId, u32, Vec<u8>, Vec<Vec<u8>>, u64),
+
+		/// Public key added to the signers of a DID Document
+		DIDKeyAdded(Vec<u8>, Vec<u8>),
+
+		/// Public key removed from the signers of a DID Document
+		DIDKeyRemoved(Vec<u8>, Vec<u8>),
+
+		/// Credential schema registered
+		SchemaRegistered(Vec<u8>, Vec<u8>, T::AccountId),
+
+		/// Credential schema registered by its author DID
+		/// Schema hash, author's DID URI
+		DIDSchemaRegistered(Vec<u8>, Vec<u8>),
+
+		/// Issuer accredited for a credential type
+		IssuerAccredited(Vec<u8>, Vec<u8>),
+
+		/// Issuer's accreditation revoked for a credential type
+		IssuerAccreditationRevoked(Vec<u8>, Vec<u8>),
+
+		/// Accreditation requirement changed
+		AccreditationRequirementSet(bool),
+
+		/// Presentation receipt anchored
+		PresentationAnchored(Vec<u8>, Vec<u8>, Vec<u8>, T::AccountId),
+
+		/// Merkle root over verifiable credential hashes anchored
+		VerifiableCredentialBatchAnchored(Vec<u8>, T::AccountId, u32),
+
+		/// Document referenced by a DID does not match its anchored hash
+		DIDRefMismatchReported(Vec<u8>, Vec<u8>),
+
+		/// Call authorised by a DID dispatched
+		/// DID URI, result of the call
+		DIDCallDispatched(Vec<u8>, DispatchResult),
+
+		/// Fees of an account's DID operations sponsored
+		/// Sponsor's AccountId, beneficiary's AccountId, allowance
+		FeesSponsored(T::AccountId, T::AccountId, BalanceOf<T>),
+
+		/// Fee sponsorship ended
+		/// Sponsor's AccountId, beneficiary's AccountId
+		FeeSponsorshipRevoked(T::AccountId, T::AccountId),
+
+		/// Controller assigned to a DID left without one
+		/// DID URI, controller's AccountId
+		DIDControllerAssigned(Vec<u8>, T::AccountId),
 	}
 
 	#[pallet::error]
@@ -208,15 +565,197 @@ pub mod pallet {
 
 		/// DID Proof not found or invalid DID URI
 		DIDProofNotFound,
+
+		/// Credential schema exists
+		SchemaExists,
+
+		/// Credential schema is not registered
+		SchemaNotRegistered,
+
+		/// Issuer is already accredited for the credential type
+		IssuerAlreadyAccredited,
+
+		/// Issuer is not accredited for the credential type
+		IssuerNotAccredited,
+
+		/// Sender is not the controller of the DID
+		NotDIDController,
+
+		/// Presentation receipt exists
+		PresentationExists,
+
+		/// Verifiable credential does not exists
+		VerifiableCredentialDoesNotExists,
+
+		/// Presentation has no consent signature of the holder
+		PresentationConsentMissing,
+
+		/// Consent is not signed by a key of the holder's DID
+		ConsentKeyNotFound,
+
+		/// Merkle root of the batch exists
+		VerifiableCredentialBatchExists,
+
+		/// Merkle root must be a 32 byte hash over at least one credential hash
+		InvalidVerifiableCredentialBatch,
+
+		/// Reported document hash matches the anchored DID document hash
+		DIDRefMatches,
+
+		/// DID reference is not a supported CID
+		InvalidDIDRef,
+
+		/// DID reference CID does not address the DID document
+		DIDRefMismatch,
+
+		/// Nonce of a call authorised by a DID is not the DID's next nonce
+		InvalidDIDNonce,
+
+		/// Fees of the account are sponsored by another account
+		FeeSponsorExists,
+
+		/// Fees of the account are not sponsored
+		FeeSponsorNotFound,
+
+		/// Sender is neither the sponsor nor the beneficiary of the fee sponsorship
+		NotFeeSponsor,
+
+		/// DID was updated after its DID reference was checked
+		DIDRefReportOutdated,
+
+		/// Credential type is given without an issuer DID accredited for it
+		CredentialTypeWithoutIssuer,
+
+		/// DID already has a controller
+		DIDControllerExists,
 	}
 
-	/// Offchain worker to support custom RPC calls to assist verifiable credentials with DIDs
-	/// TODO: Functionality will be implemented in MVP stage
+	/// Storage migrations on runtime upgrade, see `migrations`
+	/// Offchain worker fetching documents referenced by `did_ref` and flagging the ones which
+	/// do not hash to the anchored DID document hash
 	#[pallet::hooks]
 	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if PalletStorageVersion::<T>::get() == Releases::V1_0_0 {
+				migrations::v2::migrate::<T>()
+			} else {
+				0
+			}
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<(), &'static str> {
+			migrations::v2::pre_migrate::<T>()
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade() -> Result<(), &'static str> {
+			migrations::v2::post_migrate::<T>()
+		}
+
 		fn offchain_worker(block_number: T::BlockNumber) {
-			log::info!("TrackBack OCW");
-			log::info!("{:?}", block_number);
+			let interval = T::DIDRefCheckInterval::get();
+			if interval.is_zero() || !(block_number % interval).is_zero() {
+				return
+			}
+
+			if let Err(e) = Self::check_did_refs() {
+				log::warn!("TrackBack OCW: {}", e);
+			}
+		}
+	}
+
+	#[pallet::validate_unsigned]
+	impl<T: Config> ValidateUnsigned for Pallet<T> {
+		type Call = Call<T>;
+
+		fn validate_unsigned(_source: TransactionSource, call: &Self::Call) -> TransactionValidity {
+			match call {
+				| Call::report_did_ref_mismatch(report, signature) =>
+					Self::validate_did_ref_report(report, signature),
+				| Call::insert_did_document_unsigned(
+					did_document,
+					_,
+					_,
+					controller,
+					did_uri,
+					did_ref,
+					signatures,
+					nonce,
+					signature,
+				) => {
+					ensure!(
+						call.encoded_size() <= T::MaxUnsignedDIDSize::get() as usize,
+						InvalidTransaction::ExhaustsResources
+					);
+					ensure!(!DIDDocument::<T>::contains_key(did_uri), InvalidTransaction::Stale);
+					Self::ensure_did_registration(
+						did_document,
+						controller,
+						did_uri,
+						did_ref,
+						signatures,
+						*nonce,
+						signature,
+					)
+					.map_err(|_| InvalidTransaction::BadProof)?;
+
+					let next_nonce = DIDNonces::<T>::get(did_uri);
+					ensure!(*nonce >= next_nonce, InvalidTransaction::Stale);
+					ensure!(
+						Self::within_registration_limits(did_uri, &signatures[0].public_key),
+						InvalidTransaction::ExhaustsResources
+					);
+
+					// Tagged like DID calls, so the ones signed with the following nonces
+					// depend on it
+					let requires = if *nonce > next_nonce {
+						sp_std::vec![did_nonce_tag(did_uri, *nonce - 1)]
+					} else {
+						Vec::new()
+					};
+					Ok(ValidTransaction {
+						priority: T::UnsignedDIDPriority::get(),
+						requires,
+						provides: sp_std::vec![did_nonce_tag(did_uri, *nonce)],
+						longevity: T::UnsignedDIDLongevity::get(),
+						propagate: true,
+					})
+				},
+				| _ => InvalidTransaction::Call.into(),
+			}
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// Reports are only accepted when signed by a current authority, for the current version
+		/// of the DID
+		fn validate_did_ref_report(
+			report: &DIDRefReport<T::AuthorityId>,
+			signature: &<T::AuthorityId as RuntimeAppPublic>::Signature,
+		) -> TransactionValidity {
+			let did = DIDDocument::<T>::get(&report.did_uri).ok_or(InvalidTransaction::Stale)?;
+			if did.version != report.version ||
+				did.did_document_hash == report.fetched_hash ||
+				DIDRefMismatches::<T>::get(&report.did_uri).as_ref() ==
+					Some(&report.fetched_hash)
+			{
+				return InvalidTransaction::Stale.into()
+			}
+
+			if !T::Authorities::get().contains(&report.authority) {
+				return InvalidTransaction::BadSigner.into()
+			}
+			if !report.using_encoded(|payload| report.authority.verify(&payload, signature)) {
+				return InvalidTransaction::BadProof.into()
+			}
+
+			ValidTransaction::with_tag_prefix("DIDRefMismatch")
+				.priority(T::UnsignedPriority::get())
+				.and_provides(&report.did_uri)
+				.longevity(T::DIDRefCheckInterval::get().unique_saturated_into())
+				.propagate(true)
+				.build()
 		}
 	}
 
@@ -224,18 +763,30 @@ pub mod pallet {
 	impl<T: Config> Pallet<T> {
 		/// Stores hashes of verifiable credentials issued per issuer's account (aka controller)
 		/// Does not store any verifiable credential or user centric data on-chain store
+		/// An issuer DID must be controlled by the sender, and accredited for the credential type
+		/// when a credential type is given or `AccreditationRequired` is set
 		#[pallet::weight(0)]
 		pub fn create_vc_fingerprint(
 			origin: OriginFor<T>,
 			public_key: Vec<u8>,
 			vc_hash: Vec<u8>,
 			active: Option<bool>,
+			schema_id: Option<Vec<u8>>,
+			issuer_did: Option<Vec<u8>>,
+			credential_type: Option<Vec<u8>>,
 		) -> DispatchResultWithPostInfo {
 			let origin_account = ensure_signed(origin)?;
 
 			// Ensures a verifiable credential finger print does not exist
 			ensure!(!VC::<T>::contains_key(&vc_hash), Error::<T>::VerifiableCredentialExists);
 
+			// A referenced schema must be registered
+			if let Some(schema) = &schema_id {
+				ensure!(Schemas::<T>::contains_key(schema), Error::<T>::SchemaNotRegistered);
+			}
+
+			Self::ensure_issuer(&origin_account, &issuer_did, &credential_type)?;
+
 			let _account =
 				T::AccountId::decode(&mut &public_key[..]).map_err(|_| "could not convert")?;
 			let time = T::TimeProvider::now().as_secs();
@@ -247,6 +798,10 @@ pub mod pallet {
 					public_key: public_key.clone(),
 					block_time_stamp: time,
 					active,
+					schema_id,
+					issuer: Some(origin_account.clone()),
+					issuer_did,
+					credential_type,
 				},
 			);
 			Self::deposit_event(Event::VerifiableCredentialFingerPrintCreated(
@@ -257,23 +812,272 @@ pub mod pallet {
 			Ok(().into())
 		}
 
+		/// Anchors a Merkle root over a batch of verifiable credential hashes
+		/// Issuers minting credentials in bulk anchor one root instead of one fingerprint per
+		/// credential, inclusion is proven off-chain with `utils::merkle`
+		#[pallet::weight(0)]
+		pub fn anchor_vc_batch(
+			origin: OriginFor<T>,
+			merkle_root: Vec<u8>,
+			leaf_count: u32,
+			issuer_did: Option<Vec<u8>>,
+			credential_type: Option<Vec<u8>>,
+		) -> DispatchResultWithPostInfo {
+			let origin_account = ensure_signed(origin)?;
+
+			ensure!(
+				merkle_root.len() == 32 && leaf_count > 0,
+				Error::<T>::InvalidVerifiableCredentialBatch
+			);
+			ensure!(
+				!VCBatches::<T>::contains_key(&merkle_root),
+				Error::<T>::VerifiableCredentialBatchExists
+			);
+
+			Self::ensure_issuer(&origin_account, &issuer_did, &credential_type)?;
+
+			let time = T::TimeProvider::now().as_secs();
+
+			VCBatches::<T>::insert(
+				merkle_root.clone(),
+				VerifiableCredentialBatch {
+					issuer: origin_account.clone(),
+					issuer_did,
+					credential_type,
+					leaf_count,
+					block_time_stamp: time,
+				},
+			);
+
+			Self::deposit_event(Event::VerifiableCredentialBatchAnchored(
+				merkle_root,
+				origin_account,
+				leaf_count,
+			));
+
+			Ok(().into())
+		}
+
+		/// Registers a JSON Schema for verifiable credentials
+		/// Only the schema hash and an optional off-chain reference are stored
+		/// The author must be an existing DID controlled by the sender
+		#[pallet::weight(0)]
+		pub fn register_schema(
+			origin: OriginFor<T>,
+			schema_hash: Vec<u8>,
+			author: Vec<u8>,
+			version: u32,
+			schema_ref: Option<Vec<u8>>,
+		) -> DispatchResultWithPostInfo {
+			let origin_account = ensure_signed(origin)?;
+
+			Self::ensure_controller(&author, &origin_account)?;
+			Self::store_schema(&schema_hash, &author, version, schema_ref)?;
+
+			Self::deposit_event(Event::SchemaRegistered(schema_hash, author, origin_account));
+
+			Ok(().into())
+		}
+
+		/// Registers a JSON Schema for verifiable credentials authored by the DID of the origin,
+		/// e.g. a call authorised by the DID with `submit_did_call`
+		#[pallet::weight(0)]
+		pub fn register_did_schema(
+			origin: OriginFor<T>,
+			schema_hash: Vec<u8>,
+			version: u32,
+			schema_ref: Option<Vec<u8>>,
+		) -> DispatchResultWithPostInfo {
+			let author = T::SchemaOrigin::ensure_origin(origin)?;
+
+			ensure!(DIDDocument::<T>::contains_key(&author), Error::<T>::DIDDoesNotExists);
+			Self::store_schema(&schema_hash, &author, version, schema_ref)?;
+
+			Self::deposit_event(Event::DIDSchemaRegistered(schema_hash, author));
+
+			Ok(().into())
+		}
+
+		/// Accredits an issuer DID for a credential type
+		#[pallet::weight(0)]
+		pub fn accredit_issuer(
+			origin: OriginFor<T>,
+			credential_type: Vec<u8>,
+			issuer_did: Vec<u8>,
+		) -> DispatchResultWithPostInfo {
+			T::AccreditationOrigin::ensure_origin(origin)?;
+
+			ensure!(DIDDocument::<T>::contains_key(&issuer_did), Error::<T>::DIDDoesNotExists);
+			ensure!(
+				!TrustedIssuers::<T>::contains_key(&credential_type, &issuer_did),
+				Error::<T>::IssuerAlreadyAccredited
+			);
+
+			let time = T::TimeProvider::now().as_secs();
+			TrustedIssuers::<T>::insert(&credential_type, &issuer_did, time);
+			IssuerAccreditations::<T>::insert(&issuer_did, &credential_type, ());
+
+			Self::deposit_event(Event::IssuerAccredited(credential_type, issuer_did));
+
+			Ok(().into())
+		}
+
+		/// Revokes an issuer's accreditation for a credential type
+		#[pallet::weight(0)]
+		pub fn revoke_accreditation(
+			origin: OriginFor<T>,
+			credential_type: Vec<u8>,
+			issuer_did: Vec<u8>,
+		) -> DispatchResultWithPostInfo {
+			T::AccreditationOrigin::ensure_origin(origin)?;
+
+			ensure!(
+				TrustedIssuers::<T>::contains_key(&credential_type, &issuer_did),
+				Error::<T>::IssuerNotAccredited
+			);
+
+			TrustedIssuers::<T>::remove(&credential_type, &issuer_did);
+			IssuerAccreditations::<T>::remove(&issuer_did, &credential_type);
+
+			Self::deposit_event(Event::IssuerAccreditationRevoked(credential_type, issuer_did));
+
+			Ok(().into())
+		}
+
+		/// Sets whether only accredited issuers can anchor verifiable credential fingerprints
+		#[pallet::weight(0)]
+		pub fn set_accreditation_required(
+			origin: OriginFor<T>,
+			required: bool,
+		) -> DispatchResultWithPostInfo {
+			T::AccreditationOrigin::ensure_origin(origin)?;
+
+			AccreditationRequired::<T>::put(required);
+
+			Self::deposit_event(Event::AccreditationRequirementSet(required));
+
+			Ok(().into())
+		}
+
+		/// Anchors a receipt of a credential presented by a holder to a verifier
+		/// The sender must control the verifier's DID
+		/// Each consent signature must sign the presentation hash with an active key of the
+		/// holder's DID
+		#[pallet::weight(0)]
+		pub fn anchor_presentation(
+			origin: OriginFor<T>,
+			presentation_hash: Vec<u8>,
+			vc_hash: Vec<u8>,
+			verifier_did: Vec<u8>,
+			holder_did: Vec<u8>,
+			mut consent: Vec<DIDSignature>,
+		) -> DispatchResultWithPostInfo {
+			let origin_account = ensure_signed(origin)?;
+
+			ensure!(
+				!Presentations::<T>::contains_key(&presentation_hash),
+				Error::<T>::PresentationExists
+			);
+			ensure!(VC::<T>::contains_key(&vc_hash), Error::<T>::VerifiableCredentialDoesNotExists);
+
+			Self::ensure_controller(&verifier_did, &origin_account)?;
+			ensure!(DIDDocument::<T>::contains_key(&holder_did), Error::<T>::DIDDoesNotExists);
+
+			ensure!(!consent.is_empty(), Error::<T>::PresentationConsentMissing);
+
+			let holder_keys: Vec<Vec<u8>> = DIDProof::<T>::get(&holder_did)
+				.unwrap_or_default()
+				.into_iter()
+				.filter(|signature| signature.active)
+				.map(|signature| signature.public_key)
+				.collect();
+
+			let time = T::TimeProvider::now().as_secs();
+
+			for signature in consent.iter_mut() {
+				ensure!(
+					holder_keys.contains(&signature.public_key),
+					Error::<T>::ConsentKeyNotFound
+				);
+				ensure!(
+					Self::verify_signature(
+						&signature.public_key,
+						&signature.proof,
+						&presentation_hash
+					),
+					Error::<T>::DIDProofVerificationFailed
+				);
+				signature.created_time_stamp = time;
+				signature.updated_time_stamp = time;
+			}
+
+			Presentations::<T>::insert(
+				presentation_hash.clone(),
+				PresentationReceipt {
+					vc_hash,
+					verifier_did: verifier_did.clone(),
+					holder_did: holder_did.clone(),
+					consent,
+					block_time_stamp: time,
+				},
+			);
+
+			Self::deposit_event(Event::PresentationAnchored(
+				presentation_hash,
+				verifier_did,
+				holder_did,
+				origin_account,
+			));
+
+			Ok(().into())
+		}
+
 		/// DID Revocation
 		/// Throws DoesNotExists for a non existing DID revocation
+		/// Only the DID's controller can revoke it, its issuer accreditations are revoked with it
 		#[pallet::weight(0)]
-		pub fn revoke_did(origin: OriginFor<T>, did_uri: Vec<u8>) -> DispatchResultWithPostInfo {
+		pub fn revoke_did(
+			origin: OriginFor<T>,
+			did_uri: Vec<u8>,
+			reason: Option<Vec<u8>>,
+		) -> DispatchResultWithPostInfo {
 			let origin_account = ensure_signed(origin)?;
 
-			ensure!(DIDDocument::<T>::contains_key(&did_uri), Error::<T>::DIDDoesNotExists);
+			Self::ensure_controller(&did_uri, &origin_account)?;
 
 			DIDDocument::<T>::remove(&did_uri);
 
-			Self::deposit_event(Event::DIDDocumentRevoked(did_uri, origin_account));
+			for (credential_type, _) in IssuerAccreditations::<T>::drain_prefix(&did_uri) {
+				TrustedIssuers::<T>::remove(&credential_type, &did_uri);
+				Self::deposit_event(Event::IssuerAccreditationRevoked(
+					credential_type,
+					did_uri.clone(),
+				));
+			}
+
+			DIDRefMismatches::<T>::remove(&did_uri);
+			if let Some(controller) = DIDController::<T>::take(&did_uri) {
+				DIDsByController::<T>::remove(&controller, &did_uri);
+			}
+			let signatures = DIDProof::<T>::get(&did_uri).unwrap_or_default();
+			Self::unindex_public_keys(&did_uri, &signatures);
+
+			for signature in signatures {
+				Self::deposit_event(Event::DIDKeyRemoved(did_uri.clone(), signature.public_key));
+			}
+			Self::deposit_event(Event::DIDDocumentRevoked(
+				did_uri,
+				origin_account,
+				reason,
+				T::TimeProvider::now().as_secs(),
+			));
 
 			Ok(().into())
 		}
 
 		/// Updates a DID document
-		#[pallet::weight(0)]
+		/// Only the DID's controller can update it
+		#[pallet::weight((0, DispatchClass::Normal, Pallet::<T>::did_operation_pays()))]
 		pub fn update_did(
 			origin: OriginFor<T>,
 			did_document: Vec<u8>,
@@ -283,52 +1087,79 @@ pub mod pallet {
 			did_ref: Option<Vec<u8>>,
 			mut signatures: Vec<DIDSignature>,
 		) -> DispatchResultWithPostInfo {
-			let _origin_account = ensure_signed(origin)?;
+			let origin_account = ensure_signed(origin)?;
+
+			Self::ensure_controller(&did_uri, &origin_account)?;
+			Self::ensure_did_ref(&did_ref, &did_document)?;
+
+			// Calls are not transactional, so every proof is checked before storage changes
+			ensure!(
+				signatures.iter().all(|signature| Self::verify_signature(
+					&signature.public_key,
+					&signature.proof,
+					&did_document
+				)),
+				Error::<T>::DIDProofVerificationFailed
+			);
 
 			let time = T::TimeProvider::now().as_secs();
+			for signature in signatures.iter_mut() {
+				signature.updated_time_stamp = time;
+			}
 
 			// TODO:- https://track-back.atlassian.net/browse/TP-258
 			// TODO: Find a better way to do this
 			// Assigning and removing signatures should update this list
-			DIDProof::<T>::remove(did_uri.clone());
+			let previous_signatures = DIDProof::<T>::take(did_uri.clone()).unwrap_or_default();
 
-			for i in 0..signatures.len() {
-				signatures[i].updated_time_stamp = time;
-
-				let proof = signatures[i].clone().proof;
-
-				let public_key =
-					ed25519::Public::try_from(&*(signatures[i].clone().public_key)).unwrap();
-
-				let did_signature: Proof = Proof::from_slice(proof.as_ref());
-				let verified = public_key.verify(&did_document, &did_signature);
+			Self::unindex_public_keys(&did_uri, &previous_signatures);
+			Self::index_public_keys(&did_uri, &signatures);
 
-				//Check Signatures with public keys
-				if !verified {
-					ensure!(verified, Error::<T>::DIDProofVerificationFailed);
-					break
-				}
-			}
+			let previous_keys: Vec<Vec<u8>> =
+				previous_signatures.into_iter().map(|signature| signature.public_key).collect();
+			let signer_keys: Vec<Vec<u8>> =
+				signatures.iter().map(|signature| signature.public_key.clone()).collect();
 
 			DIDProof::<T>::insert(did_uri.clone(), signatures);
 
-			DIDDocument::<T>::mutate(did_uri.clone(), |did| match did {
+			let did_document_hash = blake2_256(&did_document).to_vec();
+			let version = DIDDocument::<T>::mutate(did_uri.clone(), |did| match did {
 				| None => return Err(Error::<T>::DIDDoesNotExists),
 				| Some(d) => {
 					d.did_resolution_metadata = did_resolution_metadata;
 					d.did_document_metadata = did_document_metadata;
 					d.did_ref = did_ref;
 					d.updated_time_stamp = time;
-					Ok(())
+					d.did_document_hash = did_document_hash.clone();
+					d.version = d.version.saturating_add(1);
+					Ok(d.version)
 				},
 			})?;
-			Self::deposit_event(Event::DIDDocumentUpdated(did_uri));
+			DIDRefMismatches::<T>::remove(&did_uri);
+
+			// Full document is kept off chain, only its hash lives in state
+			sp_io::offchain_index::set(&offchain::document_key(&did_uri, version), &did_document);
+
+			for key in previous_keys.iter().filter(|key| !signer_keys.contains(key)) {
+				Self::deposit_event(Event::DIDKeyRemoved(did_uri.clone(), key.clone()));
+			}
+			for key in signer_keys.iter().filter(|key| !previous_keys.contains(key)) {
+				Self::deposit_event(Event::DIDKeyAdded(did_uri.clone(), key.clone()));
+			}
+			Self::deposit_event(Event::DIDDocumentUpdated(
+				did_uri,
+				origin_account,
+				version,
+				did_document_hash,
+				signer_keys,
+				time,
+			));
 
 			Ok(().into())
 		}
 
 		/// Stores a DID document
-		#[pallet::weight(0)]
+		#[pallet::weight((0, DispatchClass::Normal, Pallet::<T>::did_operation_pays()))]
 		pub fn insert_did_document(
 			origin: OriginFor<T>,
 			did_document: Vec<u8>,
@@ -345,6 +1176,7 @@ pub mod pallet {
 			let time = T::TimeProvider::now().as_secs();
 
 			ensure!(!DIDDocument::<T>::contains_key(&did_uri), Error::<T>::DIDExists);
+			Self::ensure_did_ref(&did_ref, &did_document)?;
 
 			for i in 0..signatures.len() {
 				signatures[i].created_time_stamp = time.clone();
@@ -352,8 +1184,8 @@ pub mod pallet {
 
 				let proof = signatures[i].clone().proof;
 
-				let public_key =
-					ed25519::Public::try_from(&*(signatures[i].clone().public_key)).unwrap();
+				let public_key = ed25519::Public::try_from(&*(signatures[i].clone().public_key))
+					.map_err(|_| Error::<T>::DIDProofVerificationFailed)?;
 
 				let did_signature: Proof = Proof::from_slice(proof.as_ref());
 				let verified = public_key.verify(&did_document, &did_signature);
@@ -371,26 +1203,686 @@ pub mod pallet {
 			let doc = str::from_utf8(&did_document).unwrap();
 			let _sanitised = doc.replace("\n", "").replace(" ", "");
 
+			let did = DID {
+				did_document_metadata,
+				did_resolution_metadata,
+				block_time_stamp: time.clone(),
+				updated_time_stamp: time,
+				did_ref,
+				sender_account_id,
+				did_document_hash: blake2_256(&did_document).to_vec(),
+				version: 1,
+			};
+			Self::store_did(did_uri, did, origin_account, &did_document, signatures);
+
+			Ok(().into())
+		}
+
+		/// Stores a DID document without an account, so holders can register themselves
+		/// * `signature` is made by the first key of `signatures` over `did_registration_payload`,
+		///   binding the controller and the nonce to the document
+		/// * `nonce` is the DID's next nonce, so registrations of revoked DIDs can not be replayed
+		/// * Checked by `ValidateUnsigned` before it enters the pool, which bounds its size, the
+		///   registrations per block, and applies the rate limits of DID operations to the DID and
+		///   the key
+		/// * The controller does not sign the registration, so it is not rate limited
+		#[pallet::weight(Pallet::<T>::unsigned_registration_weight(
+			did_document.len(),
+			signatures.len(),
+		))]
+		pub fn insert_did_document_unsigned(
+			origin: OriginFor<T>,
+			did_document: Vec<u8>,
+			did_document_metadata: Option<Vec<u8>>,
+			did_resolution_metadata: Option<Vec<u8>>,
+			controller: T::AccountId,
+			did_uri: Vec<u8>,
+			did_ref: Option<Vec<u8>>,
+			mut signatures: Vec<DIDSignature>,
+			nonce: u64,
+			signature: Proof,
+		) -> DispatchResultWithPostInfo {
+			ensure_none(origin)?;
+
+			Self::ensure_did_registration(
+				&did_document,
+				&controller,
+				&did_uri,
+				&did_ref,
+				&signatures,
+				nonce,
+				&signature,
+			)?;
+			ensure!(nonce == DIDNonces::<T>::get(&did_uri), Error::<T>::InvalidDIDNonce);
+			DIDNonces::<T>::insert(&did_uri, nonce.saturating_add(1));
+			Self::note_registration(&did_uri, &signatures[0].public_key);
+
+			let time = T::TimeProvider::now().as_secs();
+			for signature in signatures.iter_mut() {
+				signature.created_time_stamp = time;
+				signature.updated_time_stamp = time;
+			}
+
+			let did = DID {
+				did_document_metadata,
+				did_resolution_metadata,
+				block_time_stamp: time,
+				updated_time_stamp: time,
+				did_ref,
+				sender_account_id: controller.encode(),
+				did_document_hash: blake2_256(&did_document).to_vec(),
+				version: 1,
+			};
+			Self::store_did(did_uri, did, controller, &did_document, signatures);
+
+			Ok(().into())
+		}
+
+		/// Flags a DID whose document referenced by `did_ref` does not hash to the anchored
+		/// document hash, submitted unsigned by offchain workers
+		/// `signature` is made by the report's authority key over the SCALE encoded report
+		#[pallet::weight(0)]
+		pub fn report_did_ref_mismatch(
+			origin: OriginFor<T>,
+			report: DIDRefReport<T::AuthorityId>,
+			// Verified by `validate_unsigned`
+			_signature: <T::AuthorityId as RuntimeAppPublic>::Signature,
+		) -> DispatchResultWithPostInfo {
+			ensure_none(origin)?;
+
+			let DIDRefReport { did_uri, version, fetched_hash, .. } = report;
+			let did = DIDDocument::<T>::get(&did_uri).ok_or(Error::<T>::DIDDoesNotExists)?;
+			ensure!(did.version == version, Error::<T>::DIDRefReportOutdated);
+			ensure!(did.did_document_hash != fetched_hash, Error::<T>::DIDRefMatches);
+
+			DIDRefMismatches::<T>::insert(&did_uri, &fetched_hash);
+
+			Self::deposit_event(Event::DIDRefMismatchReported(did_uri, fetched_hash));
+
+			Ok(().into())
+		}
+
+		/// Dispatches a call with the origin of a DID
+		/// * `signature` is made by an active key of the DID's proof over `did_call_payload`
+		/// * Any account may submit it and pays its fee, the DID's nonce is bumped even when the
+		///   call fails
+		/// * Calls accepting a DID origin are configured with `EnsureDid`, e.g. `SchemaOrigin` for
+		///   `register_did_schema`, other calls fail with `BadOrigin`
+		#[pallet::weight({
+			let dispatch_info = call.get_dispatch_info();
+			(dispatch_info.weight.saturating_add(10_000), dispatch_info.class)
+		})]
+		pub fn submit_did_call(
+			origin: OriginFor<T>,
+			did_uri: Vec<u8>,
+			public_key: Vec<u8>,
+			nonce: u64,
+			call: Box<<T as Config>::Call>,
+			signature: Proof,
+		) -> DispatchResultWithPostInfo {
+			ensure_signed(origin)?;
+
+			Self::authorize_did_call(&did_uri, &public_key, nonce, &call, &signature)?;
+			ensure!(nonce == DIDNonces::<T>::get(&did_uri), Error::<T>::InvalidDIDNonce);
+			DIDNonces::<T>::insert(&did_uri, nonce.saturating_add(1));
+
+			let result = call.dispatch(RawOrigin::DID(did_uri.clone()).into());
+
+			Self::deposit_event(Event::DIDCallDispatched(
+				did_uri,
+				result.map(|_| ()).map_err(|e| e.error),
+			));
+
+			Ok(().into())
+		}
+
+		/// Pays the fees of `beneficiary`'s DID operations up to `allowance`, see `fees`
+		/// * The sponsor resets the allowance by calling it again
+		/// * An account has a single sponsor at a time
+		#[pallet::weight(0)]
+		pub fn sponsor_fees(
+			origin: OriginFor<T>,
+			beneficiary: T::AccountId,
+			allowance: BalanceOf<T>,
+		) -> DispatchResultWithPostInfo {
+			let sponsor = ensure_signed(origin)?;
+
+			FeeSponsors::<T>::try_mutate(&beneficiary, |sponsorship| match sponsorship {
+				| Some((current, _)) if current != &sponsor => Err(Error::<T>::FeeSponsorExists),
+				| _ => {
+					*sponsorship = Some((sponsor.clone(), allowance));
+					Ok(())
+				},
+			})?;
+
+			Self::deposit_event(Event::FeesSponsored(sponsor, beneficiary, allowance));
+
+			Ok(().into())
+		}
+
+		/// Ends a fee sponsorship, by the sponsor or the beneficiary
+		#[pallet::weight(0)]
+		pub fn revoke_fee_sponsorship(
+			origin: OriginFor<T>,
+			beneficiary: T::AccountId,
+		) -> DispatchResultWithPostInfo {
+			let sender = ensure_signed(origin)?;
+
+			let (sponsor, _) =
+				FeeSponsors::<T>::get(&beneficiary).ok_or(Error::<T>::FeeSponsorNotFound)?;
+			ensure!(sender == sponsor || sender == beneficiary, Error::<T>::NotFeeSponsor);
+
+			FeeSponsors::<T>::remove(&beneficiary);
+
+			Self::deposit_event(Event::FeeSponsorshipRevoked(sponsor, beneficiary));
+
+			Ok(().into())
+		}
+
+		/// Assigns a controller to a DID without one, e.g. a DID migrated from the first release
+		/// whose sender account could not be decoded
+		#[pallet::weight(0)]
+		pub fn assign_did_controller(
+			origin: OriginFor<T>,
+			did_uri: Vec<u8>,
+			controller: T::AccountId,
+		) -> DispatchResultWithPostInfo {
+			ensure_root(origin)?;
+
+			ensure!(DIDDocument::<T>::contains_key(&did_uri), Error::<T>::DIDDoesNotExists);
+			ensure!(!DIDController::<T>::contains_key(&did_uri), Error::<T>::DIDControllerExists);
+
+			DIDController::<T>::insert(&did_uri, &controller);
+			DIDsByController::<T>::insert(&controller, &did_uri, ());
+
+			Self::deposit_event(Event::DIDControllerAssigned(did_uri, controller));
+
+			Ok(().into())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// Returns the DID URIs of issuers accredited for a credential type
+		pub fn trusted_issuers(credential_type: Vec<u8>) -> Vec<Vec<u8>> {
+			TrustedIssuers::<T>::iter_prefix(credential_type)
+				.map(|(issuer, _)| issuer)
+				.collect()
+		}
+
+		/// Returns the batch a verifiable credential hash is proven to be included in
+		pub fn verify_batch_inclusion(
+			merkle_root: Vec<u8>,
+			vc_hash: Vec<u8>,
+			proof: Vec<Vec<u8>>,
+		) -> Option<VerifiableCredentialBatch<T>> {
+			VCBatches::<T>::get(&merkle_root)
+				.filter(|batch| merkle::verify(&merkle_root, &vc_hash, &proof, batch.leaf_count))
+		}
+
+		/// Returns the DID URIs controlled by an Account
+		pub fn dids_by_controller(controller: T::AccountId) -> Vec<Vec<u8>> {
+			DIDsByController::<T>::iter_prefix(controller)
+				.map(|(did_uri, _)| did_uri)
+				.collect()
+		}
+
+		/// Returns the DID URIs signed by a public key
+		pub fn dids_by_public_key(public_key: Vec<u8>) -> Vec<Vec<u8>> {
+			DIDsByPublicKey::<T>::iter_prefix(public_key)
+				.map(|(did_uri, _)| did_uri)
+				.collect()
+		}
+
+		/// Whether a DID is stored and not revoked
+		pub fn is_active(did_uri: &[u8]) -> bool {
+			DIDDocument::<T>::contains_key(did_uri)
+		}
+
+		/// Whether a public key is an active key of a DID's proof
+		pub fn is_did_key(did_uri: &[u8], public_key: &[u8]) -> bool {
+			DIDProof::<T>::get(did_uri)
+				.unwrap_or_default()
+				.iter()
+				.any(|proof| proof.active && proof.public_key == public_key)
+		}
+
+		/// Whether a message is signed by an active key of an active DID, signatures of the wrong
+		/// length never verify
+		pub fn verify_did_signature(
+			did_uri: &[u8],
+			public_key: &[u8],
+			message: &[u8],
+			signature: &[u8],
+		) -> bool {
+			Self::is_active(did_uri) &&
+				Self::is_did_key(did_uri, public_key) &&
+				Proof::try_from(signature)
+					.map(|signature| Self::verify_signature(public_key, &signature, message))
+					.unwrap_or(false)
+		}
+
+		/// Returns the status of an anchored verifiable credential, checking its issuer's DID and
+		/// accreditation as of now
+		pub fn vc_status(vc_hash: &[u8]) -> Option<VerifiableCredentialStatus> {
+			VC::<T>::get(vc_hash).map(|vc| {
+				let issuer_did_active = vc.issuer_did.as_ref().map(|did| Self::is_active(did));
+				let issuer_accredited = match (&vc.issuer_did, &vc.credential_type) {
+					| (Some(issuer), Some(credential_type)) =>
+						Some(TrustedIssuers::<T>::contains_key(credential_type, issuer)),
+					| _ => None,
+				};
+				VerifiableCredentialStatus {
+					active: vc.active,
+					issuer_did_active,
+					issuer_accredited,
+					valid: vc.active != Some(false) &&
+						issuer_did_active != Some(false) &&
+						issuer_accredited != Some(false),
+				}
+			})
+		}
+
+		/// Fetches the documents referenced by a few DIDs and reports the ones not matching their
+		/// anchored hash, DIDs anchored before document hashes were recorded are skipped
+		/// Nodes without a key of a current authority do not check DIDs
+		fn check_did_refs() -> Result<(), &'static str> {
+			let endpoint = match offchain::endpoint() {
+				| Some(endpoint) => endpoint,
+				| None => return Ok(()),
+			};
+			let authorities = T::Authorities::get();
+			let authority =
+				match T::AuthorityId::all().into_iter().find(|key| authorities.contains(key)) {
+					| Some(authority) => authority,
+					| None => return Ok(()),
+				};
+
+			// Resumes after the last DID checked, and starts over once all were checked
+			let cursor = offchain::cursor();
+			let dids: Vec<(Vec<u8>, DID)> = if cursor.is_empty() {
+				DIDDocument::<T>::iter().take(offchain::DID_REFS_PER_RUN).collect()
+			} else {
+				DIDDocument::<T>::iter_from(cursor).take(offchain::DID_REFS_PER_RUN).collect()
+			};
+			offchain::set_cursor(match dids.last() {
+				| Some((did_uri, _)) if dids.len() == offchain::DID_REFS_PER_RUN =>
+					DIDDocument::<T>::hashed_key_for(did_uri),
+				| _ => Vec::new(),
+			});
+
+			for (did_uri, did) in dids {
+				let did_ref = match did.did_ref {
+					| Some(did_ref) if !did.did_document_hash.is_empty() => did_ref,
+					| _ => continue,
+				};
+
+				let document = match offchain::fetch(&endpoint, &did_ref) {
+					| Ok(document) => document,
+					| Err(e) => {
+						log::warn!("TrackBack OCW: unable to fetch a DID document {:?}", e);
+						continue
+					},
+				};
+
+				let fetched_hash = blake2_256(&document).to_vec();
+				if fetched_hash == did.did_document_hash ||
+					DIDRefMismatches::<T>::get(&did_uri).as_ref() == Some(&fetched_hash)
+				{
+					continue
+				}
+
+				let report = DIDRefReport {
+					did_uri,
+					version: did.version,
+					fetched_hash,
+					authority: authority.clone(),
+				};
+				let signature = report
+					.using_encoded(|payload| authority.sign(&payload))
+					.ok_or("Unable to sign a DID reference mismatch")?;
+				let call = Call::report_did_ref_mismatch(report, signature);
+				SubmitTransaction::<T, Call<T>>::submit_unsigned_transaction(call.into())
+					.map_err(|_| "Unable to submit a DID reference mismatch")?;
+			}
+
+			Ok(())
+		}
+
+		/// Ensures the DID reference, when given, is a CID addressing the DID document
+		fn ensure_did_ref(did_ref: &Option<Vec<u8>>, did_document: &[u8]) -> DispatchResult {
+			if let Some(did_ref) = did_ref {
+				let cid = Cid::parse(did_ref).ok_or(Error::<T>::InvalidDIDRef)?;
+				ensure!(cid.matches(did_document), Error::<T>::DIDRefMismatch);
+			}
+			Ok(())
+		}
+
+		/// Indexes a DID by the public keys of its signatures
+		fn index_public_keys(did_uri: &[u8], signatures: &[DIDSignature]) {
+			for signature in signatures {
+				DIDsByPublicKey::<T>::insert(&signature.public_key, did_uri, ());
+			}
+		}
+
+		/// Removes a DID from the index of the public keys of its signatures
+		fn unindex_public_keys(did_uri: &[u8], signatures: &[DIDSignature]) {
+			for signature in signatures {
+				DIDsByPublicKey::<T>::remove(&signature.public_key, did_uri);
+			}
+		}
+
+		/// Stores a credential schema which is not registered yet
+		fn store_schema(
+			schema_hash: &[u8],
+			author: &[u8],
+			version: u32,
+			schema_ref: Option<Vec<u8>>,
+		) -> DispatchResult {
+			ensure!(!Schemas::<T>::contains_key(schema_hash), Error::<T>::SchemaExists);
+
+			Schemas::<T>::insert(
+				schema_hash,
+				CredentialSchema {
+					author: author.to_vec(),
+					version,
+					schema_ref,
+					block_time_stamp: T::TimeProvider::now().as_secs(),
+				},
+			);
+
+			Ok(())
+		}
+
+		/// Ensures a DID exists and is controlled by `who`
+		pub(crate) fn ensure_controller(did_uri: &[u8], who: &T::AccountId) -> DispatchResult {
+			ensure!(DIDDocument::<T>::contains_key(did_uri), Error::<T>::DIDDoesNotExists);
+			ensure!(
+				DIDController::<T>::get(did_uri).as_ref() == Some(who),
+				Error::<T>::NotDIDController
+			);
+			Ok(())
+		}
+
+		/// Ensures the sender controls the issuer DID it claims, and that the issuer is accredited
+		/// for the credential type when one is given or accreditation is required, a credential
+		/// type needs an issuer DID
+		fn ensure_issuer(
+			origin_account: &T::AccountId,
+			issuer_did: &Option<Vec<u8>>,
+			credential_type: &Option<Vec<u8>>,
+		) -> DispatchResult {
+			if let Some(issuer) = issuer_did {
+				Self::ensure_controller(issuer, origin_account)?;
+			}
+
+			match (issuer_did, credential_type) {
+				| (Some(issuer), Some(credential_type)) => ensure!(
+					TrustedIssuers::<T>::contains_key(credential_type, issuer),
+					Error::<T>::IssuerNotAccredited
+				),
+				| (None, Some(_)) => return Err(Error::<T>::CredentialTypeWithoutIssuer.into()),
+				| _ => ensure!(!AccreditationRequired::<T>::get(), Error::<T>::IssuerNotAccredited),
+			}
+
+			Ok(())
+		}
+
+		/// Payload signed by a DID key to authorise a call, SCALE encoded
+		/// `(did_uri, nonce, call)`
+		pub fn did_call_payload(did_uri: &[u8], nonce: u64, call: &<T as Config>::Call) -> Vec<u8> {
+			(did_uri, nonce, call).encode()
+		}
+
+		/// Ensures the DID exists and the call is signed by an active key of its proof
+		pub(crate) fn authorize_did_call(
+			did_uri: &[u8],
+			public_key: &[u8],
+			nonce: u64,
+			call: &<T as Config>::Call,
+			signature: &Proof,
+		) -> DispatchResult {
+			ensure!(DIDDocument::<T>::contains_key(did_uri), Error::<T>::DIDDoesNotExists);
+			ensure!(Self::is_did_key(did_uri, public_key), Error::<T>::DIDProofNotFound);
+			ensure!(
+				Self::verify_signature(
+					public_key,
+					signature,
+					&Self::did_call_payload(did_uri, nonce, call)
+				),
+				Error::<T>::DIDProofVerificationFailed
+			);
+			Ok(())
+		}
+
+		/// Returns the account paying `fee` for a DID operation of `who`, the allowance of its
+		/// sponsor is drawn when it covers the fee
+		pub(crate) fn draw_fee_allowance(who: &T::AccountId, fee: BalanceOf<T>) -> T::AccountId {
+			FeeSponsors::<T>::mutate(who, |sponsorship| match sponsorship {
+				| Some((sponsor, allowance)) if *allowance >= fee => {
+					*allowance = allowance.saturating_sub(fee);
+					sponsor.clone()
+				},
+				| _ => who.clone(),
+			})
+		}
+
+		/// Returns the refund of a fee paid by a sponsor to its allowance
+		pub(crate) fn refund_fee_allowance(
+			who: &T::AccountId,
+			sponsor: &T::AccountId,
+			refund: BalanceOf<T>,
+		) {
+			FeeSponsors::<T>::mutate(who, |sponsorship| {
+				if let Some((current, allowance)) = sponsorship {
+					if current == sponsor {
+						*allowance = allowance.saturating_add(refund);
+					}
+				}
+			})
+		}
+
+		/// Payload signed by the first key of a DID registered with
+		/// `insert_did_document_unsigned`, SCALE encoded `(did_uri, did_document, controller,
+		/// nonce)`
+		pub fn did_registration_payload(
+			did_uri: &[u8],
+			did_document: &[u8],
+			controller: &T::AccountId,
+			nonce: u64,
+		) -> Vec<u8> {
+			(did_uri, did_document, controller, nonce).encode()
+		}
+
+		/// Ensures an unsigned DID registration is new and authenticated by its proofs, the nonce
+		/// is checked by the caller
+		fn ensure_did_registration(
+			did_document: &[u8],
+			controller: &T::AccountId,
+			did_uri: &[u8],
+			did_ref: &Option<Vec<u8>>,
+			signatures: &[DIDSignature],
+			nonce: u64,
+			signature: &Proof,
+		) -> DispatchResult {
+			ensure!(!DIDDocument::<T>::contains_key(did_uri), Error::<T>::DIDExists);
+			Self::ensure_did_ref(did_ref, did_document)?;
+			ensure!(
+				Self::proofs_verify(did_document, signatures) &&
+					Self::verify_signature(
+						&signatures[0].public_key,
+						signature,
+						&Self::did_registration_payload(did_uri, did_document, controller, nonce),
+					),
+				Error::<T>::DIDProofVerificationFailed
+			);
+			Ok(())
+		}
+
+		/// Stores a new DID with its proofs, indexes it and emits its creation events
+		fn store_did(
+			did_uri: Vec<u8>,
+			did: DID,
+			controller: T::AccountId,
+			did_document: &[u8],
+			signatures: Vec<DIDSignature>,
+		) {
 			// Inserts new set of signatures.
 			// DID URI can have one or more signatures
 			// This should decide by the controller
+			Self::index_public_keys(&did_uri, &signatures);
+			let signer_keys: Vec<Vec<u8>> =
+				signatures.iter().map(|signature| signature.public_key.clone()).collect();
 			DIDProof::<T>::insert(did_uri.clone(), signatures);
 
-			DIDDocument::<T>::insert(
-				did_uri.clone(),
-				DID {
-					did_document_metadata,
-					did_resolution_metadata,
-					block_time_stamp: time.clone(),
-					updated_time_stamp: time,
-					did_ref,
-					sender_account_id,
+			DIDController::<T>::insert(&did_uri, &controller);
+			DIDsByController::<T>::insert(&controller, &did_uri, ());
+
+			let (did_document_hash, time) = (did.did_document_hash.clone(), did.block_time_stamp);
+			DIDDocument::<T>::insert(did_uri.clone(), did);
+
+			// Full document is kept off chain, only its hash lives in state
+			sp_io::offchain_index::set(&offchain::document_key(&did_uri, 1), did_document);
+
+			for key in signer_keys.iter() {
+				Self::deposit_event(Event::DIDKeyAdded(did_uri.clone(), key.clone()));
+			}
+			Self::deposit_event(Event::DIDDocumentCreated(
+				did_uri,
+				controller,
+				1,
+				did_document_hash,
+				signer_keys,
+				time,
+			));
+		}
+
+		/// Fees of `insert_did_document` and `update_did`
+		fn did_operation_pays() -> Pays {
+			if T::FeelessDIDOperations::get() {
+				Pays::No
+			} else {
+				Pays::Yes
+			}
+		}
+
+		/// Returns whether a DID document has proofs and all of them verify
+		pub(crate) fn proofs_verify(did_document: &[u8], signatures: &[DIDSignature]) -> bool {
+			!signatures.is_empty() &&
+				signatures.iter().all(|signature| {
+					Self::verify_signature(&signature.public_key, &signature.proof, did_document)
+				})
+		}
+
+		/// Current DID operation rate limit era, `None` when the limits are disabled
+		fn operation_era() -> Option<T::BlockNumber> {
+			let era = T::DIDOperationEra::get();
+			if era.is_zero() {
+				None
+			} else {
+				Some(<frame_system::Module<T>>::block_number() / era)
+			}
+		}
+
+		/// Returns whether a DID and an account may make another DID operation in this era
+		pub(crate) fn within_operation_limits(who: &T::AccountId, did_uri: &[u8]) -> bool {
+			let era = match Self::operation_era() {
+				| Some(era) => era,
+				| None => return true,
+			};
+			let count =
+				|(last_era, count): (T::BlockNumber, u32)| if last_era == era { count } else { 0 };
+			let max = T::MaxDIDOperationsPerEra::get();
+
+			count(DIDOperations::<T>::get(did_uri)) < max &&
+				count(AccountOperations::<T>::get(who)) < max
+		}
+
+		/// Counts a DID operation of a DID and an account in this era
+		pub(crate) fn note_operation(who: &T::AccountId, did_uri: &[u8]) {
+			if let Some(era) = Self::operation_era() {
+				let note = |operations: &mut (T::BlockNumber, u32)| {
+					*operations = match *operations {
+						| (last_era, count) if last_era == era => (era, count.saturating_add(1)),
+						| _ => (era, 1),
+					}
+				};
+				DIDOperations::<T>::mutate(did_uri, note);
+				AccountOperations::<T>::mutate(who, note);
+			}
+		}
+
+		/// Returns whether a DID may be registered without an account in this block, and for a DID
+		/// and a public key signing it in this era
+		fn within_registration_limits(did_uri: &[u8], public_key: &[u8]) -> bool {
+			let within_block_limit = match UnsignedDIDRegistrations::<T>::get() {
+				| (block, count) if block == <frame_system::Module<T>>::block_number() =>
+					count < T::MaxUnsignedDIDsPerBlock::get(),
+				| _ => true,
+			};
+			let within_era_limits = match Self::operation_era() {
+				| Some(era) => {
+					let count = |(last_era, count): (T::BlockNumber, u32)| {
+						if last_era == era {
+							count
+						} else {
+							0
+						}
+					};
+					let max = T::MaxDIDOperationsPerEra::get();
+
+					count(DIDOperations::<T>::get(did_uri)) < max &&
+						count(KeyOperations::<T>::get(public_key)) < max
 				},
-			);
+				| None => true,
+			};
+			within_block_limit && within_era_limits
+		}
 
-			Self::deposit_event(Event::DIDDocumentCreated(did_uri, origin_account));
+		/// Counts a DID registration without an account in this block, and like a DID operation of
+		/// the DID and of the public key signing it
+		fn note_registration(did_uri: &[u8], public_key: &[u8]) {
+			let block = <frame_system::Module<T>>::block_number();
+			UnsignedDIDRegistrations::<T>::mutate(|registrations| {
+				*registrations = match *registrations {
+					| (last_block, count) if last_block == block =>
+						(block, count.saturating_add(1)),
+					| _ => (block, 1),
+				}
+			});
+
+			if let Some(era) = Self::operation_era() {
+				let note = |operations: &mut (T::BlockNumber, u32)| {
+					*operations = match *operations {
+						| (last_era, count) if last_era == era => (era, count.saturating_add(1)),
+						| _ => (era, 1),
+					}
+				};
+				DIDOperations::<T>::mutate(did_uri, note);
+				KeyOperations::<T>::mutate(public_key, note);
+			}
+		}
 
-			Ok(().into())
+		/// Weight of `insert_did_document_unsigned`, verifying the proofs and the registration
+		/// signature over the document, then storing and indexing the DID
+		pub fn unsigned_registration_weight(document_len: usize, signatures: usize) -> Weight {
+			// ed25519 verification of a short message, and hashing of each document byte
+			const VERIFY_WEIGHT: Weight = 60_000_000;
+			const BYTE_WEIGHT: Weight = 2_000;
+
+			let verify =
+				VERIFY_WEIGHT.saturating_add(BYTE_WEIGHT.saturating_mul(document_len as Weight));
+			let db = T::DbWeight::get();
+			verify
+				.saturating_mul(signatures.saturating_add(1) as Weight)
+				.saturating_add(db.reads_writes(6, 9))
+				.saturating_add(db.writes(signatures as Weight))
+		}
+
+		/// Verifies an ed25519 signature, malformed public keys never verify
+		pub fn verify_signature(public_key: &[u8], proof: &Proof, message: &[u8]) -> bool {
+			ed25519::Public::try_from(public_key)
+				.map(|public_key| public_key.verify(&message, proof))
+				.unwrap_or(false)
 		}
 	}
 }
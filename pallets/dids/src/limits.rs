@@ -0,0 +1,102 @@
+// This file is part of TrackBck which is released under GNU General Public License v3.0.
+// See file LICENSE.md or go to https://www.gnu.org/licenses/gpl-3.0.en.html for full license details.
+//! Rate limits of DID operations
+//! * A DID and an account may make `MaxDIDOperationsPerEra` calls to `insert_did_document` and
+//!   `update_did` every `DIDOperationEra` blocks
+//! * With `FeelessDIDOperations` these calls are free, so their proofs are checked before they
+//!   enter the pool, and free updates are only admitted from the DID's controller, which
+//!   `update_did` requires as well
+
+use crate::pallet::{Call, Config, Pallet};
+use codec::{Decode, Encode};
+use frame_support::traits::{Get, IsSubType};
+use sp_runtime::{
+	traits::{DispatchInfoOf, SignedExtension},
+	transaction_validity::{
+		InvalidTransaction, TransactionValidity, TransactionValidityError, ValidTransaction,
+	},
+};
+use sp_std::{fmt, marker::PhantomData};
+
+/// Enforces the rate limits of DID operations and checks the proofs of feeless ones
+#[derive(Encode, Decode, Clone, Eq, PartialEq)]
+pub struct CheckDidOperationLimits<T: Config + Send + Sync>(PhantomData<T>);
+
+impl<T: Config + Send + Sync> CheckDidOperationLimits<T> {
+	pub fn new() -> Self {
+		Self(PhantomData)
+	}
+}
+
+impl<T: Config + Send + Sync> Default for CheckDidOperationLimits<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T: Config + Send + Sync> fmt::Debug for CheckDidOperationLimits<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "CheckDidOperationLimits")
+	}
+}
+
+impl<T: Config + Send + Sync> SignedExtension for CheckDidOperationLimits<T>
+where
+	<T as frame_system::Config>::Call: IsSubType<Call<T>>,
+{
+	const IDENTIFIER: &'static str = "CheckDidOperationLimits";
+	type AccountId = T::AccountId;
+	type Call = <T as frame_system::Config>::Call;
+	type AdditionalSigned = ();
+	type Pre = ();
+
+	fn additional_signed(&self) -> Result<(), TransactionValidityError> {
+		Ok(())
+	}
+
+	fn validate(
+		&self,
+		who: &Self::AccountId,
+		call: &Self::Call,
+		_info: &DispatchInfoOf<Self::Call>,
+		_len: usize,
+	) -> TransactionValidity {
+		let (did_uri, authenticated) = match call.is_sub_type() {
+			| Some(Call::insert_did_document(did_document, _, _, _, did_uri, _, signatures)) =>
+				(did_uri, Pallet::<T>::proofs_verify(did_document, signatures)),
+			| Some(Call::update_did(did_document, did_uri, _, _, _, signatures)) => (
+				did_uri,
+				Pallet::<T>::ensure_controller(did_uri, who).is_ok() &&
+					Pallet::<T>::proofs_verify(did_document, signatures),
+			),
+			| _ => return Ok(ValidTransaction::default()),
+		};
+
+		if T::FeelessDIDOperations::get() && !authenticated {
+			return InvalidTransaction::BadProof.into()
+		}
+		if !Pallet::<T>::within_operation_limits(who, did_uri) {
+			return InvalidTransaction::ExhaustsResources.into()
+		}
+
+		Ok(ValidTransaction::default())
+	}
+
+	fn pre_dispatch(
+		self,
+		who: &Self::AccountId,
+		call: &Self::Call,
+		info: &DispatchInfoOf<Self::Call>,
+		len: usize,
+	) -> Result<(), TransactionValidityError> {
+		self.validate(who, call, info, len)?;
+
+		match call.is_sub_type() {
+			| Some(Call::insert_did_document(_, _, _, _, did_uri, _, _)) |
+			Some(Call::update_did(_, did_uri, _, _, _, _)) => Pallet::<T>::note_operation(who, did_uri),
+			| _ => {},
+		}
+
+		Ok(())
+	}
+}
@@ -0,0 +1,209 @@
+// This file is part of TrackBck which is released under GNU General Public License v3.0.
+// See file LICENSE.md or go to https://www.gnu.org/licenses/gpl-3.0.en.html for full license details.
+//! Storage migrations of the DID pallet
+//! * `PalletStorageVersion` records the storage layout, `on_runtime_upgrade` migrates from it
+//! * Each migration keeps the layouts it reads from, so structs in `structs` can keep changing
+//! * `pre_upgrade` / `post_upgrade` checks run with the `try-runtime` feature, `post_upgrade`
+//!   compares the numbers of values with the ones recorded by `pre_upgrade`
+
+use codec::{Decode, DecodeAll, Encode};
+use sp_std::vec::Vec;
+
+/// Checks every value stored under a map's prefix decodes as `V`
+#[cfg(feature = "try-runtime")]
+fn all_decode<V: Decode>(prefix: &[u8]) -> bool {
+	let mut key = prefix.to_vec();
+	while let Some(next) = sp_io::storage::next_key(&key) {
+		if !next.starts_with(prefix) {
+			break
+		}
+		match sp_io::storage::get(&next) {
+			| Some(value) if V::decode(&mut &value[..]).is_ok() => {},
+			| _ => return false,
+		}
+		key = next;
+	}
+	true
+}
+
+/// Number of values stored under a map's prefix
+#[cfg(feature = "try-runtime")]
+fn count(prefix: &[u8]) -> u32 {
+	let mut key = prefix.to_vec();
+	let mut count = 0;
+	while let Some(next) = sp_io::storage::next_key(&key) {
+		if !next.starts_with(prefix) {
+			break
+		}
+		count += 1;
+		key = next;
+	}
+	count
+}
+
+/// Migrates the layout of the first release to `Releases::V2_0_0`
+/// * DIDs gain a document hash and a version, the hash of documents anchored before is unknown and
+///   left empty, so the offchain worker skips them until they are updated
+/// * Verifiable credentials gain a schema, an issuer, an issuer DID and a credential type
+/// * DIDs are indexed by the public keys of their proofs
+/// * The caller supplied `sender_account_id` becomes the controller when it is exactly an encoded
+///   account, other DIDs are left without a controller until `assign_did_controller` gives them one
+/// * The never written `DIDs` map is removed
+pub mod v2 {
+	use super::*;
+	use crate::{
+		pallet::{
+			Config, DIDController, DIDDocument, DIDProof, DIDsByController, DIDsByPublicKey,
+			Pallet, PalletStorageVersion, VC,
+		},
+		structs::{Releases, VerifiableCredential, DID},
+	};
+	use frame_support::{
+		storage::migration::remove_storage_prefix,
+		traits::{Get, PalletInfo},
+		weights::Weight,
+	};
+
+	/// DID layout of the first release
+	#[derive(Decode, Encode)]
+	pub struct OldDID {
+		pub did_resolution_metadata: Option<Vec<u8>>,
+		pub did_document_metadata: Option<Vec<u8>>,
+		pub block_time_stamp: u64,
+		pub updated_time_stamp: u64,
+		pub did_ref: Option<Vec<u8>>,
+		pub sender_account_id: Vec<u8>,
+	}
+
+	/// Verifiable credential layout of the first release
+	#[derive(Decode, Encode)]
+	pub struct OldVerifiableCredential<AccountId> {
+		pub account_id: Option<AccountId>,
+		pub public_key: Vec<u8>,
+		pub block_time_stamp: u64,
+		pub active: Option<bool>,
+	}
+
+	pub fn migrate<T: Config>() -> Weight {
+		let mut reads_writes: u64 = 0;
+
+		DIDDocument::<T>::translate::<OldDID, _>(|did_uri, old| {
+			reads_writes += 1;
+			if let Ok(controller) = T::AccountId::decode_all(&old.sender_account_id) {
+				reads_writes += 2;
+				DIDController::<T>::insert(&did_uri, &controller);
+				DIDsByController::<T>::insert(&controller, &did_uri, ());
+			}
+			Some(DID {
+				did_resolution_metadata: old.did_resolution_metadata,
+				did_document_metadata: old.did_document_metadata,
+				block_time_stamp: old.block_time_stamp,
+				updated_time_stamp: old.updated_time_stamp,
+				did_ref: old.did_ref,
+				sender_account_id: old.sender_account_id,
+				did_document_hash: Vec::new(),
+				version: 1,
+			})
+		});
+
+		VC::<T>::translate::<OldVerifiableCredential<T::AccountId>, _>(|_, old| {
+			reads_writes += 1;
+			Some(VerifiableCredential {
+				account_id: old.account_id,
+				public_key: old.public_key,
+				block_time_stamp: old.block_time_stamp,
+				active: old.active,
+				schema_id: None,
+				issuer: None,
+				issuer_did: None,
+				credential_type: None,
+			})
+		});
+
+		for (did_uri, signatures) in DIDProof::<T>::iter() {
+			reads_writes += 1;
+			for signature in signatures {
+				reads_writes += 1;
+				DIDsByPublicKey::<T>::insert(&signature.public_key, &did_uri, ());
+			}
+		}
+
+		if let Some(name) = <T as frame_system::Config>::PalletInfo::name::<Pallet<T>>() {
+			remove_storage_prefix(name.as_bytes(), b"DIDs", &[]);
+		}
+
+		PalletStorageVersion::<T>::put(Releases::V2_0_0);
+
+		T::DbWeight::get().reads_writes(reads_writes + 1, reads_writes + 2)
+	}
+
+	/// Numbers of DIDs and verifiable credentials before the migration, kept from `pre_migrate`
+	/// to `post_migrate`
+	#[cfg(feature = "try-runtime")]
+	const COUNTS_KEY: &[u8] = b":trackback:dids:migration:v2:counts";
+
+	#[cfg(feature = "try-runtime")]
+	pub fn pre_migrate<T: Config>() -> Result<(), &'static str> {
+		use frame_support::storage::StoragePrefixedMap;
+
+		if PalletStorageVersion::<T>::get() != Releases::V1_0_0 {
+			return Ok(())
+		}
+		frame_support::ensure!(
+			all_decode::<OldDID>(&DIDDocument::<T>::final_prefix()),
+			"DIDs do not decode with the first release layout"
+		);
+		frame_support::ensure!(
+			all_decode::<OldVerifiableCredential<T::AccountId>>(&VC::<T>::final_prefix()),
+			"Verifiable credentials do not decode with the first release layout"
+		);
+
+		let counts = (count(&DIDDocument::<T>::final_prefix()), count(&VC::<T>::final_prefix()));
+		sp_io::storage::set(COUNTS_KEY, &counts.encode());
+		Ok(())
+	}
+
+	#[cfg(feature = "try-runtime")]
+	pub fn post_migrate<T: Config>() -> Result<(), &'static str> {
+		use frame_support::storage::StoragePrefixedMap;
+
+		frame_support::ensure!(
+			PalletStorageVersion::<T>::get() == Releases::V2_0_0,
+			"Storage version was not updated"
+		);
+		frame_support::ensure!(
+			all_decode::<DID>(&DIDDocument::<T>::final_prefix()),
+			"DIDs do not decode after the migration"
+		);
+		frame_support::ensure!(
+			all_decode::<VerifiableCredential<T>>(&VC::<T>::final_prefix()),
+			"Verifiable credentials do not decode after the migration"
+		);
+
+		// Values which do not decode are dropped by `translate`, none may be lost
+		if let Some(counts) = sp_io::storage::get(COUNTS_KEY) {
+			sp_io::storage::clear(COUNTS_KEY);
+			let (dids, vcs) = <(u32, u32)>::decode(&mut &counts[..])
+				.map_err(|_| "Counts before the migration do not decode")?;
+			frame_support::ensure!(
+				count(&DIDDocument::<T>::final_prefix()) == dids,
+				"DIDs were lost by the migration"
+			);
+			frame_support::ensure!(
+				count(&VC::<T>::final_prefix()) == vcs,
+				"Verifiable credentials were lost by the migration"
+			);
+		}
+
+		let without_controller = DIDDocument::<T>::iter()
+			.filter(|(did_uri, _)| !DIDController::<T>::contains_key(did_uri))
+			.count();
+		if without_controller > 0 {
+			log::warn!(
+				"{} DIDs have no controller, assign them one with `assign_did_controller`",
+				without_controller
+			);
+		}
+		Ok(())
+	}
+}
@@ -1,12 +1,14 @@
 // This file is part of TrackBck which is released under GNU General Public License v3.0.
 // See file LICENSE.md or go to https://www.gnu.org/licenses/gpl-3.0.en.html for full license details.
 use crate as pallet_dids;
-use frame_support::parameter_types;
+use frame_support::{parameter_types, weights::IdentityFee};
 use frame_system as system;
+use frame_system::{EnsureOneOf, EnsureRoot};
 use sp_core::H256;
 use sp_runtime::{
-	testing::Header,
+	testing::{Header, TestXt, UintAuthorityId},
 	traits::{BlakeTwo256, IdentityLookup},
+	transaction_validity::{TransactionLongevity, TransactionPriority},
 };
 
 type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
@@ -20,8 +22,10 @@ frame_support::construct_runtime!(
 		UncheckedExtrinsic = UncheckedExtrinsic,
 	{
 		System: frame_system::{Module, Call, Config, Storage, Event<T>},
-		DIDModule: pallet_dids::{Module, Call, Storage, Event<T>},
+		DIDModule: pallet_dids::{Module, Call, Storage, Event<T>, Origin, ValidateUnsigned},
 		Timestamp: pallet_timestamp::{Module, Call, Storage, Inherent},
+		Balances: pallet_balances::{Module, Call, Storage, Config<T>, Event<T>},
+		TransactionPayment: pallet_transaction_payment::{Module, Storage},
 	}
 );
 
@@ -48,7 +52,7 @@ impl frame_system::Config for Test {
 	type BlockHashCount = BlockHashCount;
 	type Version = ();
 	type PalletInfo = PalletInfo;
-	type AccountData = ();
+	type AccountData = pallet_balances::AccountData<u64>;
 	type OnNewAccount = ();
 	type OnKilledAccount = ();
 	type SystemWeightInfo = ();
@@ -65,9 +69,71 @@ impl pallet_timestamp::Config for Test {
 	type WeightInfo = ();
 }
 
+parameter_types! {
+	pub const ExistentialDeposit: u64 = 1;
+	pub const TransactionByteFee: u64 = 1;
+}
+
+impl pallet_balances::Config for Test {
+	type MaxLocks = ();
+	type Balance = u64;
+	type Event = Event;
+	type DustRemoval = ();
+	type ExistentialDeposit = ExistentialDeposit;
+	type AccountStore = System;
+	type WeightInfo = ();
+}
+
+impl pallet_transaction_payment::Config for Test {
+	type OnChargeTransaction = pallet_dids::SponsoredCurrencyAdapter<()>;
+	type TransactionByteFee = TransactionByteFee;
+	type WeightToFee = IdentityFee<u64>;
+	type FeeMultiplierUpdate = ();
+}
+
+pub type Extrinsic = TestXt<Call, ()>;
+
+impl<C> frame_system::offchain::SendTransactionTypes<C> for Test
+where
+	Call: From<C>,
+{
+	type OverarchingCall = Call;
+	type Extrinsic = Extrinsic;
+}
+
+parameter_types! {
+	pub const UnsignedPriority: TransactionPriority = TransactionPriority::max_value();
+	pub const DIDRefCheckInterval: u64 = 1;
+	pub const UnsignedDIDPriority: TransactionPriority = 100;
+	pub const UnsignedDIDLongevity: TransactionLongevity = 64;
+	pub const FeelessDIDOperations: bool = true;
+	pub const DIDOperationEra: u64 = 10;
+	pub const MaxDIDOperationsPerEra: u32 = 2;
+	pub const MaxUnsignedDIDSize: u32 = 2 * 1024;
+	pub const MaxUnsignedDIDsPerBlock: u32 = 3;
+	pub Authorities: Vec<UintAuthorityId> = vec![UintAuthorityId(1), UintAuthorityId(2)];
+}
+
 impl pallet_dids::Config for Test {
 	type Event = Event;
 	type TimeProvider = pallet_timestamp::Pallet<Test>;
+	type Currency = Balances;
+	type Origin = Origin;
+	type Call = Call;
+	// DIDs accredit issuers in tests to exercise `EnsureDid`
+	type AccreditationOrigin = EnsureOneOf<u64, EnsureRoot<u64>, pallet_dids::EnsureDid>;
+	type SchemaOrigin = pallet_dids::EnsureDid;
+	type AuthorityId = UintAuthorityId;
+	type Authorities = Authorities;
+	type UnsignedPriority = UnsignedPriority;
+	type UnsignedDIDPriority = UnsignedDIDPriority;
+	type UnsignedDIDLongevity = UnsignedDIDLongevity;
+	type DIDRefCheckInterval = DIDRefCheckInterval;
+	type FeelessDIDOperations = FeelessDIDOperations;
+	type DIDOperationEra = DIDOperationEra;
+	type MaxDIDOperationsPerEra = MaxDIDOperationsPerEra;
+	type MaxUnsignedDIDSize = MaxUnsignedDIDSize;
+	type MaxUnsignedDIDsPerBlock = MaxUnsignedDIDsPerBlock;
 }
 
 // Build genesis storage according to the mock runtime.
@@ -0,0 +1,70 @@
+// This file is part of TrackBck which is released under GNU General Public License v3.0.
+// See file LICENSE.md or go to https://www.gnu.org/licenses/gpl-3.0.en.html for full license details.
+//! Offchain helpers for DID housekeeping
+//! * Documents referenced by `did_ref` are fetched from `<gateway>/ipfs/<cid>`, like the
+//!   `did_resolve` RPC does, the IPFS gateway (e.g. `http://127.0.0.1:8080`) is stored under the
+//!   persistent offchain storage key `DID_REF_ENDPOINT`
+//! * Operators set it with the `offchain_localStorageSet` RPC, checks are skipped when unset
+//! * Only authorities check DIDs, their reports are signed with their authority key
+//! * A few DIDs are checked per run, the storage key of the last one checked is kept in offchain
+//!   storage and the next run resumes after it
+//! * Full DID documents are indexed off chain under `document_key`
+
+use crate::utils::cid;
+use sp_core::offchain::StorageKind;
+use sp_runtime::offchain::{http, storage::StorageValueRef, Duration};
+use sp_std::{str, vec::Vec};
+
+/// Persistent offchain storage key of the IPFS gateway serving DID documents
+pub const DID_REF_ENDPOINT: &[u8] = b"trackback::dids::did-ref-endpoint";
+
+/// Persistent offchain storage key of the storage key of the last DID checked
+pub const DID_REF_CURSOR: &[u8] = b"trackback::dids::did-ref-cursor";
+
+pub use pallet_dids_runtime_api::{document_key, DID_DOCUMENT_PREFIX};
+
+/// Number of DIDs checked per offchain worker run
+pub const DID_REFS_PER_RUN: usize = 5;
+
+/// Time allowed to fetch a single DID document
+const FETCH_TIMEOUT_MS: u64 = 3_000;
+
+/// Returns the configured gateway, stored as raw bytes
+pub fn endpoint() -> Option<Vec<u8>> {
+	sp_io::offchain::local_storage_get(StorageKind::PERSISTENT, DID_REF_ENDPOINT)
+		.filter(|endpoint| !endpoint.is_empty())
+}
+
+/// Returns the storage key of the last DID checked, empty to start from the first DID
+pub fn cursor() -> Vec<u8> {
+	StorageValueRef::persistent(DID_REF_CURSOR)
+		.get::<Vec<u8>>()
+		.flatten()
+		.unwrap_or_default()
+}
+
+/// Stores the storage key of the last DID checked
+pub fn set_cursor(cursor: &[u8]) {
+	StorageValueRef::persistent(DID_REF_CURSOR).set(&cursor);
+}
+
+/// Fetches the document referenced by `did_ref`, optionally `ipfs://` prefixed, from the gateway
+pub fn fetch(gateway: &[u8], did_ref: &[u8]) -> Result<Vec<u8>, http::Error> {
+	let gateway = str::from_utf8(gateway).map_err(|_| http::Error::Unknown)?;
+	let cid = str::from_utf8(cid::strip_scheme(did_ref)).map_err(|_| http::Error::Unknown)?;
+	let url = [gateway.trim_end_matches('/'), "/ipfs/", cid].concat();
+
+	let deadline = sp_io::offchain::timestamp().add(Duration::from_millis(FETCH_TIMEOUT_MS));
+	let pending = http::Request::get(&url)
+		.deadline(deadline)
+		.send()
+		.map_err(|_| http::Error::IoError)?;
+	let response = pending.try_wait(deadline).map_err(|_| http::Error::DeadlineReached)??;
+
+	if response.code != 200 {
+		log::warn!("Unexpected status code {} fetching a DID document", response.code);
+		return Err(http::Error::Unknown)
+	}
+
+	Ok(response.body().collect::<Vec<u8>>())
+}
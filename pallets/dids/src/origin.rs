@@ -0,0 +1,131 @@
+// This file is part of TrackBck which is released under GNU General Public License v3.0.
+// See file LICENSE.md or go to https://www.gnu.org/licenses/gpl-3.0.en.html for full license details.
+//! DIDs as extrinsic origins
+//! * `submit_did_call` dispatches a call with `RawOrigin::DID`, authorised by an active key of the
+//!   DID's proof rather than by the submitting account
+//! * `CheckDidAuthorization` checks the DID, the key and the nonce before the pool accepts it
+//! * Other pallets accept DID origins with `ensure_did` or `EnsureDid`
+
+use crate::pallet::{Call, Config, DIDNonces, Pallet};
+use codec::{Decode, Encode};
+use frame_support::{
+	traits::{EnsureOrigin, IsSubType},
+	RuntimeDebug,
+};
+use sp_runtime::{
+	traits::{BadOrigin, DispatchInfoOf, SignedExtension},
+	transaction_validity::{
+		InvalidTransaction, TransactionValidity, TransactionValidityError, ValidTransaction,
+	},
+};
+use sp_std::{fmt, marker::PhantomData, vec, vec::Vec};
+
+/// Origin of calls authorised by a DID
+#[derive(PartialEq, Eq, Clone, RuntimeDebug, Encode, Decode)]
+pub enum RawOrigin {
+	/// DID URI authorising the call
+	DID(Vec<u8>),
+}
+
+/// Ensures an origin is a DID, returning its URI
+pub fn ensure_did<OuterOrigin>(o: OuterOrigin) -> Result<Vec<u8>, BadOrigin>
+where
+	OuterOrigin: Into<Result<RawOrigin, OuterOrigin>>,
+{
+	match o.into() {
+		| Ok(RawOrigin::DID(did_uri)) => Ok(did_uri),
+		| Err(_) => Err(BadOrigin),
+	}
+}
+
+/// Origin check succeeding with the URI of any DID origin
+pub struct EnsureDid;
+
+impl<O: Into<Result<RawOrigin, O>> + From<RawOrigin>> EnsureOrigin<O> for EnsureDid {
+	type Success = Vec<u8>;
+
+	fn try_origin(o: O) -> Result<Self::Success, O> {
+		o.into().map(|RawOrigin::DID(did_uri)| did_uri)
+	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn successful_origin() -> O {
+		O::from(RawOrigin::DID(Vec::new()))
+	}
+}
+
+/// Transaction pool tag of the call a DID authorises with `nonce`, calls with the following nonce
+/// require it
+pub fn did_nonce_tag(did_uri: &[u8], nonce: u64) -> Vec<u8> {
+	(b"DIDCall", did_uri, nonce).encode()
+}
+
+/// Checks `submit_did_call` before it enters the pool
+/// * The DID must exist and the call must be signed by an active key of its proof
+/// * Stale nonces are rejected, calls of a DID are ordered by nonce
+#[derive(Encode, Decode, Clone, Eq, PartialEq)]
+pub struct CheckDidAuthorization<T: Config + Send + Sync>(PhantomData<T>);
+
+impl<T: Config + Send + Sync> CheckDidAuthorization<T> {
+	pub fn new() -> Self {
+		Self(PhantomData)
+	}
+}
+
+impl<T: Config + Send + Sync> Default for CheckDidAuthorization<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T: Config + Send + Sync> fmt::Debug for CheckDidAuthorization<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "CheckDidAuthorization")
+	}
+}
+
+impl<T: Config + Send + Sync> SignedExtension for CheckDidAuthorization<T>
+where
+	<T as frame_system::Config>::Call: IsSubType<Call<T>>,
+{
+	const IDENTIFIER: &'static str = "CheckDidAuthorization";
+	type AccountId = T::AccountId;
+	type Call = <T as frame_system::Config>::Call;
+	type AdditionalSigned = ();
+	type Pre = ();
+
+	fn additional_signed(&self) -> Result<(), TransactionValidityError> {
+		Ok(())
+	}
+
+	fn validate(
+		&self,
+		_who: &Self::AccountId,
+		call: &Self::Call,
+		_info: &DispatchInfoOf<Self::Call>,
+		_len: usize,
+	) -> TransactionValidity {
+		let (did_uri, public_key, nonce, did_call, signature) = match call.is_sub_type() {
+			| Some(Call::submit_did_call(did_uri, public_key, nonce, did_call, signature)) =>
+				(did_uri, public_key, *nonce, did_call, signature),
+			| _ => return Ok(ValidTransaction::default()),
+		};
+
+		Pallet::<T>::authorize_did_call(did_uri, public_key, nonce, did_call, signature)
+			.map_err(|_| InvalidTransaction::BadProof)?;
+
+		let next_nonce = DIDNonces::<T>::get(did_uri);
+		if nonce < next_nonce {
+			return InvalidTransaction::Stale.into()
+		}
+
+		let requires =
+			if nonce > next_nonce { vec![did_nonce_tag(did_uri, nonce - 1)] } else { vec![] };
+
+		Ok(ValidTransaction {
+			requires,
+			provides: vec![did_nonce_tag(did_uri, nonce)],
+			..Default::default()
+		})
+	}
+}
@@ -12,6 +12,7 @@ use sp_std::vec::Vec;
 /// Stores Signatures by DID Controllers
 /// A DID can have at least a controller
 #[derive(Clone, Decode, Encode, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub struct DIDSignature {
 	pub public_key: Vec<u8>,
 	pub proof: Signature,
@@ -42,11 +43,17 @@ pub struct DID {
 
 	// Updated timestamp
 	pub updated_time_stamp: u64,
-	// IPFS  URI of the DID document
+	// IPFS CID of the DID document, see `utils::cid`
 	pub did_ref: Option<Vec<u8>>,
 
 	// Sender AccountId
 	pub sender_account_id: Vec<u8>,
+
+	// Blake2 256 hash of the DID document
+	pub did_document_hash: Vec<u8>,
+
+	// Incremented on every update, documents are indexed off chain per version
+	pub version: u32,
 }
 
 #[derive(Clone, Decode, Encode, Eq, PartialEq)]
@@ -63,12 +70,167 @@ pub struct VerifiableCredential<T: Config> {
 
 	// active
 	pub active: Option<bool>,
+
+	// Hash of the registered schema the credential conforms to
+	pub schema_id: Option<Vec<u8>>,
+
+	// Issuer's AccountId which anchored the fingerprint
+	pub issuer: Option<T::AccountId>,
+
+	// Issuer's DID URI
+	pub issuer_did: Option<Vec<u8>>,
+
+	// Credential type the issuer is accredited for
+	pub credential_type: Option<Vec<u8>>,
+}
+
+/// Status of an anchored verifiable credential, as of the current block
+#[derive(Clone, Decode, Encode, Eq, PartialEq, Debug)]
+pub struct VerifiableCredentialStatus {
+	// Status anchored with the fingerprint
+	pub active: Option<bool>,
+
+	// Whether the issuer's DID exists, `None` without an issuer DID
+	pub issuer_did_active: Option<bool>,
+
+	// Whether the issuer is accredited for the credential type, `None` without one
+	pub issuer_accredited: Option<bool>,
+
+	// Neither revoked, nor issued by a missing or unaccredited issuer
+	pub valid: bool,
+}
+
+/// DID seeded at genesis, its document is only used to verify the proofs and is not kept
+#[derive(Clone, Decode, Encode, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct GenesisDID<AccountId> {
+	// DID URI
+	pub did_uri: Vec<u8>,
+
+	// DID document the proofs are signed over
+	pub did_document: Vec<u8>,
+
+	// Controller's AccountId
+	pub controller: AccountId,
+
+	// IPFS CID of the DID document
+	pub did_ref: Option<Vec<u8>>,
+
+	// DID Document Metadata
+	pub did_document_metadata: Option<Vec<u8>>,
+
+	// DID Resolution Metadata
+	pub did_resolution_metadata: Option<Vec<u8>>,
+
+	// Proofs of the DID document
+	pub signatures: Vec<DIDSignature>,
+}
+
+/// DID whose document fetched from `did_ref` does not hash to its anchored document hash
+/// Signed by the authority key of the offchain worker which fetched it
+#[derive(Clone, Decode, Encode, Eq, PartialEq, Debug)]
+pub struct DIDRefReport<AuthorityId> {
+	// DID URI
+	pub did_uri: Vec<u8>,
+
+	// Version of the DID the document was checked against
+	pub version: u32,
+
+	// Hash of the fetched document
+	pub fetched_hash: Vec<u8>,
+
+	// Authority key signing the report
+	pub authority: AuthorityId,
+}
+
+/// Storage layouts of the pallet, see `migrations`
+#[derive(Clone, Copy, Decode, Encode, Eq, PartialEq, Debug)]
+pub enum Releases {
+	// DIDs and verifiable credentials of the first release
+	V1_0_0,
+
+	// DIDs carry document hashes and versions, credentials carry schemas and issuers
+	V2_0_0,
+}
+
+/// Storage written before versioning has the first release layout
+impl Default for Releases {
+	fn default() -> Self {
+		Releases::V1_0_0
+	}
+}
+
+/// JSON Schema published by an issuer
+/// Only the hash and an optional off-chain reference are stored on chain
+#[derive(Clone, Decode, Encode, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct CredentialSchema {
+	// DID URI of the schema author
+	pub author: Vec<u8>,
+
+	// Schema version
+	pub version: u32,
+
+	// Off-chain reference of the schema document
+	pub schema_ref: Option<Vec<u8>>,
+
+	// Created time
+	pub block_time_stamp: u64,
+}
+
+/// Merkle root anchored over a batch of verifiable credential hashes
+#[derive(Clone, Decode, Encode, Eq, PartialEq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct VerifiableCredentialBatch<T: Config> {
+	// Issuer's AccountId which anchored the batch
+	pub issuer: T::AccountId,
+
+	// Issuer's DID URI
+	pub issuer_did: Option<Vec<u8>>,
+
+	// Credential type the issuer is accredited for
+	pub credential_type: Option<Vec<u8>>,
+
+	// Number of verifiable credential hashes in the batch
+	pub leaf_count: u32,
+
+	// Created time
+	pub block_time_stamp: u64,
+}
+
+/// Receipt of a verifiable credential presented by a holder to a verifier
+/// Only hashes and DID URIs are stored, no personal data
+#[derive(Clone, Decode, Encode, Eq, PartialEq, Debug)]
+pub struct PresentationReceipt {
+	// Hash of the presented verifiable credential
+	pub vc_hash: Vec<u8>,
+
+	// Verifier's DID URI
+	pub verifier_did: Vec<u8>,
+
+	// Holder's DID URI
+	pub holder_did: Vec<u8>,
+
+	// Holder's consent signatures over the presentation hash
+	pub consent: Vec<DIDSignature>,
+
+	// Anchored time
+	pub block_time_stamp: u64,
 }
 
 /// Defaults for VerifiableCredentials
 impl<T: Config> Default for VerifiableCredential<T> {
 	fn default() -> Self {
-		Self { account_id: None, public_key: Vec::new(), block_time_stamp: 0, active: Some(false) }
+		Self {
+			account_id: None,
+			public_key: Vec::new(),
+			block_time_stamp: 0,
+			active: Some(false),
+			schema_id: None,
+			issuer: None,
+			issuer_did: None,
+			credential_type: None,
+		}
 	}
 }
 
@@ -95,6 +257,8 @@ impl Default for DID {
 			updated_time_stamp: 0,
 			did_ref: None,
 			sender_account_id: Vec::new(),
+			did_document_hash: Vec::new(),
+			version: 0,
 		}
 	}
 }
@@ -4,15 +4,51 @@ use crate::mock::{new_test_ext, Origin};
 
 use rstest::*;
 
-use crate::{mock::DIDModule, structs::DIDSignature};
-use codec::Encode;
+use crate::{
+	migrations::v2::{OldDID, OldVerifiableCredential},
+	mock::{Balances, Call, DIDModule, Event, Extrinsic, System, Test},
+	offchain,
+	structs::{
+		DIDRefReport, DIDSignature, GenesisDID, Releases, VerifiableCredential,
+		VerifiableCredentialStatus, DID,
+	},
+	utils::{cid::Cid, merkle},
+};
+use codec::{Decode, Encode};
 use frame_support::{
 	assert_err, assert_ok,
 	pallet_prelude::DispatchError,
 	sp_runtime::app_crypto::{sp_core::Hasher, Pair},
+	storage::migration::{have_storage_value, put_storage_value},
+	traits::{Currency, GenesisBuild, Hooks},
+	unsigned::ValidateUnsigned,
+	weights::{DispatchInfo, GetDispatchInfo, Pays, PostDispatchInfo},
+	Blake2_128Concat, StorageHasher,
 };
+use pallet_transaction_payment::OnChargeTransaction;
+use parking_lot::RwLock;
 use rand::{distributions::Alphanumeric, thread_rng, Rng};
-use sp_core::{ed25519, ed25519::Pair as KeyPair, Blake2Hasher};
+use sp_core::{
+	ed25519,
+	ed25519::Pair as KeyPair,
+	offchain::{
+		testing::{
+			OffchainState, PendingRequest, PoolState, TestOffchainExt, TestTransactionPoolExt,
+		},
+		OffchainExt, StorageKind, TransactionPoolExt,
+	},
+	Blake2Hasher,
+};
+use sp_io::hashing::blake2_256;
+use sp_runtime::{
+	testing::{TestSignature, UintAuthorityId},
+	traits::{BadOrigin, Dispatchable, SignedExtension},
+	transaction_validity::{
+		InvalidTransaction, TransactionSource, TransactionValidityError, ValidTransaction,
+	},
+	RuntimeAppPublic,
+};
+use std::sync::Arc;
 
 /// Fixture to generate a keypair, secret and a peerId
 #[fixture]
@@ -47,7 +83,8 @@ pub fn signature(key_pair: KeyPair, did_document: &'static str) -> Vec<DIDSignat
 
 #[fixture]
 pub fn did_ref() -> Option<Vec<u8>> {
-	Some(r#"{QmcNYMJBhvbrH8oTo5QGNUFA5rhKpBVXHBpfiecxso7D8P}"#.as_bytes().to_vec())
+	// CIDv0 of `did_document` as added to IPFS
+	Some("QmXm9GMQFvtQhdf7itRYiukYikBR2UAzvUbZmxqbagGsUU".as_bytes().to_vec())
 }
 
 #[fixture]
@@ -128,7 +165,10 @@ fn create_vc(public_key: Vec<u8>, vc_hash: Vec<u8>) {
 			Origin::signed(1),
 			public_key,
 			vc_hash,
-			Some(true)
+			Some(true),
+			None,
+			None,
+			None
 		));
 	});
 }
@@ -141,11 +181,22 @@ fn create_vc_exists(public_key: Vec<u8>, vc_hash: Vec<u8>) {
 			public_key.clone(),
 			vc_hash.clone(),
 			Some(true),
+			None,
+			None,
+			None,
 		)
 		.ok();
 
 		assert_err!(
-			DIDModule::create_vc_fingerprint(Origin::signed(1), public_key, vc_hash, Some(true)),
+			DIDModule::create_vc_fingerprint(
+				Origin::signed(1),
+				public_key,
+				vc_hash,
+				Some(true),
+				None,
+				None,
+				None
+			),
 			DispatchError::Module {
 				index: 1,
 				error: 4,
@@ -155,6 +206,189 @@ fn create_vc_exists(public_key: Vec<u8>, vc_hash: Vec<u8>) {
 	});
 }
 
+#[fixture]
+pub fn schema_hash() -> Vec<u8> {
+	Blake2Hasher::hash(r#"{"$schema": "http://json-schema.org/draft-07/schema#"}"#.as_ref())
+		.as_bytes()
+		.to_vec()
+}
+
+#[rstest]
+fn register_schema(
+	did_document_metadata: Option<Vec<u8>>,
+	did_resolution_metadata: Option<Vec<u8>>,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	did_ref: Option<Vec<u8>>,
+	public_key: Vec<u8>,
+	signature: Vec<DIDSignature>,
+	schema_hash: Vec<u8>,
+) {
+	new_test_ext().execute_with(|| {
+		DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			did_document_metadata,
+			did_resolution_metadata,
+			public_key,
+			did_uri.clone(),
+			did_ref,
+			signature,
+		)
+		.ok();
+
+		assert_ok!(DIDModule::register_schema(
+			Origin::signed(1),
+			schema_hash.clone(),
+			did_uri.clone(),
+			1,
+			None
+		));
+		assert_eq!(DIDModule::get_schema(schema_hash).map(|s| s.author), Some(did_uri));
+	});
+}
+
+#[rstest]
+fn register_an_existing_schema(
+	did_document_metadata: Option<Vec<u8>>,
+	did_resolution_metadata: Option<Vec<u8>>,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	did_ref: Option<Vec<u8>>,
+	public_key: Vec<u8>,
+	signature: Vec<DIDSignature>,
+	schema_hash: Vec<u8>,
+) {
+	new_test_ext().execute_with(|| {
+		DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			did_document_metadata,
+			did_resolution_metadata,
+			public_key,
+			did_uri.clone(),
+			did_ref,
+			signature,
+		)
+		.ok();
+		DIDModule::register_schema(
+			Origin::signed(1),
+			schema_hash.clone(),
+			did_uri.clone(),
+			1,
+			None,
+		)
+		.ok();
+
+		assert_err!(
+			DIDModule::register_schema(Origin::signed(1), schema_hash, did_uri, 2, None),
+			DispatchError::Module { index: 1, error: 7, message: Some("SchemaExists") }
+		);
+	});
+}
+
+#[rstest]
+fn register_schema_for_a_did_of_another_controller(
+	did_document_metadata: Option<Vec<u8>>,
+	did_resolution_metadata: Option<Vec<u8>>,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	did_ref: Option<Vec<u8>>,
+	public_key: Vec<u8>,
+	signature: Vec<DIDSignature>,
+	schema_hash: Vec<u8>,
+) {
+	new_test_ext().execute_with(|| {
+		DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			did_document_metadata,
+			did_resolution_metadata,
+			public_key,
+			did_uri.clone(),
+			did_ref,
+			signature,
+		)
+		.ok();
+
+		assert_err!(
+			DIDModule::register_schema(Origin::signed(2), schema_hash.clone(), did_uri, 1, None),
+			DispatchError::Module { index: 1, error: 11, message: Some("NotDIDController") }
+		);
+		assert!(DIDModule::get_schema(schema_hash).is_none());
+	});
+}
+
+#[rstest]
+fn register_schema_without_author_did(did_uri: Vec<u8>, schema_hash: Vec<u8>) {
+	new_test_ext().execute_with(|| {
+		assert_err!(
+			DIDModule::register_schema(Origin::signed(1), schema_hash, did_uri, 1, None),
+			DispatchError::Module { index: 1, error: 1, message: Some("DIDDoesNotExists") }
+		);
+	});
+}
+
+#[rstest]
+fn create_vc_with_registered_schema(
+	did_document_metadata: Option<Vec<u8>>,
+	did_resolution_metadata: Option<Vec<u8>>,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	did_ref: Option<Vec<u8>>,
+	public_key: Vec<u8>,
+	signature: Vec<DIDSignature>,
+	schema_hash: Vec<u8>,
+	vc_hash: Vec<u8>,
+) {
+	new_test_ext().execute_with(|| {
+		DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			did_document_metadata,
+			did_resolution_metadata,
+			public_key.clone(),
+			did_uri.clone(),
+			did_ref,
+			signature,
+		)
+		.ok();
+		DIDModule::register_schema(Origin::signed(1), schema_hash.clone(), did_uri, 1, None).ok();
+
+		assert_ok!(DIDModule::create_vc_fingerprint(
+			Origin::signed(1),
+			public_key,
+			vc_hash.clone(),
+			Some(true),
+			Some(schema_hash.clone()),
+			None,
+			None
+		));
+		assert_eq!(
+			DIDModule::get_verifiable_credential_hash(vc_hash).and_then(|vc| vc.schema_id),
+			Some(schema_hash)
+		);
+	});
+}
+
+#[rstest]
+fn create_vc_with_unregistered_schema(public_key: Vec<u8>, vc_hash: Vec<u8>, schema_hash: Vec<u8>) {
+	new_test_ext().execute_with(|| {
+		assert_err!(
+			DIDModule::create_vc_fingerprint(
+				Origin::signed(1),
+				public_key,
+				vc_hash,
+				Some(true),
+				Some(schema_hash),
+				None,
+				None
+			),
+			DispatchError::Module { index: 1, error: 8, message: Some("SchemaNotRegistered") }
+		);
+	});
+}
+
 /// Creates a DID with Valid Signature
 /// Single Controller for a DID Document
 #[rstest]
@@ -279,7 +513,7 @@ fn revoke_a_did(
 		)
 		.ok();
 
-		assert_ok!(DIDModule::revoke_did(Origin::signed(1), did_uri));
+		assert_ok!(DIDModule::revoke_did(Origin::signed(1), did_uri, None));
 	});
 }
 
@@ -306,7 +540,7 @@ fn revoke_non_existing_did(
 		)
 		.ok();
 
-		DIDModule::revoke_did(Origin::signed(1), did_uri.clone()).ok();
+		DIDModule::revoke_did(Origin::signed(1), did_uri.clone(), None).ok();
 	});
 }
 
@@ -314,7 +548,7 @@ fn revoke_non_existing_did(
 fn revoke_a_revoked_did(did_uri: Vec<u8>) {
 	new_test_ext().execute_with(|| {
 		assert_err!(
-			DIDModule::revoke_did(Origin::signed(1), did_uri),
+			DIDModule::revoke_did(Origin::signed(1), did_uri, None),
 			DispatchError::Module { index: 1, error: 1, message: Some("DIDDoesNotExists") }
 		);
 	});
@@ -392,3 +626,2245 @@ fn update_non_exsited_did(
 		);
 	});
 }
+
+#[fixture]
+pub fn controller() -> Vec<u8> {
+	1u64.encode()
+}
+
+#[fixture]
+pub fn credential_type() -> Vec<u8> {
+	"UniversityDegreeCredential".as_bytes().to_vec()
+}
+
+#[rstest]
+fn accredit_issuer(
+	did_document_metadata: Option<Vec<u8>>,
+	did_resolution_metadata: Option<Vec<u8>>,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	did_ref: Option<Vec<u8>>,
+	controller: Vec<u8>,
+	signature: Vec<DIDSignature>,
+	credential_type: Vec<u8>,
+) {
+	new_test_ext().execute_with(|| {
+		DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			did_document_metadata,
+			did_resolution_metadata,
+			controller,
+			did_uri.clone(),
+			did_ref,
+			signature,
+		)
+		.ok();
+
+		assert_err!(
+			DIDModule::accredit_issuer(Origin::signed(1), credential_type.clone(), did_uri.clone()),
+			DispatchError::BadOrigin
+		);
+		assert_ok!(DIDModule::accredit_issuer(
+			Origin::root(),
+			credential_type.clone(),
+			did_uri.clone()
+		));
+		assert_eq!(DIDModule::trusted_issuers(credential_type.clone()), vec![did_uri.clone()]);
+		assert_err!(
+			DIDModule::accredit_issuer(Origin::root(), credential_type, did_uri),
+			DispatchError::Module { index: 1, error: 9, message: Some("IssuerAlreadyAccredited") }
+		);
+	});
+}
+
+#[rstest]
+fn revoke_accreditation(
+	did_document_metadata: Option<Vec<u8>>,
+	did_resolution_metadata: Option<Vec<u8>>,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	did_ref: Option<Vec<u8>>,
+	controller: Vec<u8>,
+	signature: Vec<DIDSignature>,
+	credential_type: Vec<u8>,
+) {
+	new_test_ext().execute_with(|| {
+		DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			did_document_metadata,
+			did_resolution_metadata,
+			controller,
+			did_uri.clone(),
+			did_ref,
+			signature,
+		)
+		.ok();
+		DIDModule::accredit_issuer(Origin::root(), credential_type.clone(), did_uri.clone()).ok();
+
+		assert_ok!(DIDModule::revoke_accreditation(
+			Origin::root(),
+			credential_type.clone(),
+			did_uri.clone()
+		));
+		assert!(DIDModule::trusted_issuers(credential_type.clone()).is_empty());
+		assert_err!(
+			DIDModule::revoke_accreditation(Origin::root(), credential_type, did_uri),
+			DispatchError::Module { index: 1, error: 10, message: Some("IssuerNotAccredited") }
+		);
+	});
+}
+
+#[rstest]
+fn revoke_a_did_revokes_its_accreditations(
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	controller: Vec<u8>,
+	signature: Vec<DIDSignature>,
+	credential_type: Vec<u8>,
+) {
+	new_test_ext().execute_with(|| {
+		DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			None,
+			None,
+			controller,
+			did_uri.clone(),
+			None,
+			signature,
+		)
+		.ok();
+		DIDModule::accredit_issuer(Origin::root(), credential_type.clone(), did_uri.clone()).ok();
+
+		assert_ok!(DIDModule::revoke_did(Origin::signed(1), did_uri.clone(), None));
+		assert!(DIDModule::trusted_issuers(credential_type.clone()).is_empty());
+		assert_eq!(DIDModule::get_trusted_issuer(&credential_type, &did_uri), None);
+	});
+}
+
+#[rstest]
+fn manage_a_did_of_another_controller(
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	public_key: Vec<u8>,
+	signature: Vec<DIDSignature>,
+	vc_hash: Vec<u8>,
+) {
+	new_test_ext().execute_with(|| {
+		// The sender account stored with the DID does not make account 2 its controller
+		DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			None,
+			None,
+			2u64.encode(),
+			did_uri.clone(),
+			None,
+			signature.clone(),
+		)
+		.ok();
+		let not_controller =
+			DispatchError::Module { index: 1, error: 11, message: Some("NotDIDController") };
+
+		assert_err!(
+			DIDModule::update_did(
+				Origin::signed(2),
+				did_document.as_bytes().to_vec(),
+				did_uri.clone(),
+				None,
+				None,
+				None,
+				signature
+			),
+			not_controller
+		);
+		assert_err!(
+			DIDModule::revoke_did(Origin::signed(2), did_uri.clone(), None),
+			not_controller
+		);
+		assert_err!(
+			DIDModule::create_vc_fingerprint(
+				Origin::signed(2),
+				public_key,
+				vc_hash,
+				Some(true),
+				None,
+				Some(did_uri.clone()),
+				None
+			),
+			not_controller
+		);
+		assert!(DIDModule::get_did_document(&did_uri).is_some());
+	});
+}
+
+#[rstest]
+fn create_vc_by_accredited_issuer(
+	did_document_metadata: Option<Vec<u8>>,
+	did_resolution_metadata: Option<Vec<u8>>,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	did_ref: Option<Vec<u8>>,
+	controller: Vec<u8>,
+	public_key: Vec<u8>,
+	signature: Vec<DIDSignature>,
+	credential_type: Vec<u8>,
+	vc_hash: Vec<u8>,
+) {
+	new_test_ext().execute_with(|| {
+		DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			did_document_metadata,
+			did_resolution_metadata,
+			controller,
+			did_uri.clone(),
+			did_ref,
+			signature,
+		)
+		.ok();
+		DIDModule::accredit_issuer(Origin::root(), credential_type.clone(), did_uri.clone()).ok();
+		DIDModule::set_accreditation_required(Origin::root(), true).ok();
+
+		assert_ok!(DIDModule::create_vc_fingerprint(
+			Origin::signed(1),
+			public_key,
+			vc_hash.clone(),
+			Some(true),
+			None,
+			Some(did_uri.clone()),
+			Some(credential_type)
+		));
+		assert_eq!(
+			DIDModule::get_verifiable_credential_hash(vc_hash).and_then(|vc| vc.issuer_did),
+			Some(did_uri)
+		);
+	});
+}
+
+#[rstest]
+fn create_vc_by_non_accredited_issuer(
+	did_document_metadata: Option<Vec<u8>>,
+	did_resolution_metadata: Option<Vec<u8>>,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	did_ref: Option<Vec<u8>>,
+	controller: Vec<u8>,
+	public_key: Vec<u8>,
+	signature: Vec<DIDSignature>,
+	credential_type: Vec<u8>,
+	vc_hash: Vec<u8>,
+) {
+	new_test_ext().execute_with(|| {
+		DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			did_document_metadata,
+			did_resolution_metadata,
+			controller,
+			did_uri.clone(),
+			did_ref,
+			signature,
+		)
+		.ok();
+
+		assert_err!(
+			DIDModule::create_vc_fingerprint(
+				Origin::signed(1),
+				public_key,
+				vc_hash,
+				Some(true),
+				None,
+				Some(did_uri),
+				Some(credential_type)
+			),
+			DispatchError::Module { index: 1, error: 10, message: Some("IssuerNotAccredited") }
+		);
+	});
+}
+
+#[rstest]
+fn create_vc_without_issuer_when_accreditation_required(public_key: Vec<u8>, vc_hash: Vec<u8>) {
+	new_test_ext().execute_with(|| {
+		assert_ok!(DIDModule::set_accreditation_required(Origin::root(), true));
+
+		assert_err!(
+			DIDModule::create_vc_fingerprint(
+				Origin::signed(1),
+				public_key,
+				vc_hash,
+				Some(true),
+				None,
+				None,
+				None
+			),
+			DispatchError::Module { index: 1, error: 10, message: Some("IssuerNotAccredited") }
+		);
+	});
+}
+
+#[rstest]
+fn create_vc_of_a_credential_type_without_issuer(
+	public_key: Vec<u8>,
+	vc_hash: Vec<u8>,
+	credential_type: Vec<u8>,
+	vc_hashes: Vec<Vec<u8>>,
+) {
+	new_test_ext().execute_with(|| {
+		assert_err!(
+			DIDModule::create_vc_fingerprint(
+				Origin::signed(1),
+				public_key,
+				vc_hash.clone(),
+				Some(true),
+				None,
+				None,
+				Some(credential_type.clone())
+			),
+			DispatchError::Module {
+				index: 1,
+				error: 26,
+				message: Some("CredentialTypeWithoutIssuer")
+			}
+		);
+		assert!(DIDModule::get_verifiable_credential_hash(vc_hash).is_none());
+
+		let merkle_root = merkle::root(&vc_hashes).unwrap();
+		assert_err!(
+			DIDModule::anchor_vc_batch(
+				Origin::signed(1),
+				merkle_root.to_vec(),
+				vc_hashes.len() as u32,
+				None,
+				Some(credential_type)
+			),
+			DispatchError::Module {
+				index: 1,
+				error: 26,
+				message: Some("CredentialTypeWithoutIssuer")
+			}
+		);
+	});
+}
+
+#[rstest]
+fn create_vc_for_an_issuer_did_of_another_controller(
+	did_document_metadata: Option<Vec<u8>>,
+	did_resolution_metadata: Option<Vec<u8>>,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	did_ref: Option<Vec<u8>>,
+	controller: Vec<u8>,
+	public_key: Vec<u8>,
+	signature: Vec<DIDSignature>,
+	credential_type: Vec<u8>,
+	vc_hash: Vec<u8>,
+) {
+	new_test_ext().execute_with(|| {
+		DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			did_document_metadata,
+			did_resolution_metadata,
+			controller,
+			did_uri.clone(),
+			did_ref,
+			signature,
+		)
+		.ok();
+		DIDModule::accredit_issuer(Origin::root(), credential_type.clone(), did_uri.clone()).ok();
+
+		assert_err!(
+			DIDModule::create_vc_fingerprint(
+				Origin::signed(2),
+				public_key,
+				vc_hash,
+				Some(true),
+				None,
+				Some(did_uri),
+				Some(credential_type)
+			),
+			DispatchError::Module { index: 1, error: 11, message: Some("NotDIDController") }
+		);
+	});
+}
+
+#[fixture]
+pub fn presentation_hash() -> Vec<u8> {
+	Blake2Hasher::hash("presentation".as_ref()).as_bytes().to_vec()
+}
+
+/// Signs a message with a key pair of a DID
+fn did_signature(key_pair: &KeyPair, message: &[u8]) -> DIDSignature {
+	DIDSignature {
+		public_key: key_pair.public().encode(),
+		proof: key_pair.sign(message),
+		active: true,
+		created_time_stamp: 0,
+		updated_time_stamp: 0,
+	}
+}
+
+/// Inserts a verifier DID controlled by account 1, a holder DID and a credential fingerprint
+fn setup_presentation(
+	did_document: &'static str,
+	verifier_did: Vec<u8>,
+	holder_did: Vec<u8>,
+	holder_key: &KeyPair,
+	vc_hash: Vec<u8>,
+) {
+	DIDModule::insert_did_document(
+		Origin::signed(1),
+		did_document.as_bytes().to_vec(),
+		None,
+		None,
+		1u64.encode(),
+		verifier_did,
+		None,
+		vec![did_signature(&ed25519::Pair::generate().0, did_document.as_bytes())],
+	)
+	.ok();
+	DIDModule::insert_did_document(
+		Origin::signed(2),
+		did_document.as_bytes().to_vec(),
+		None,
+		None,
+		2u64.encode(),
+		holder_did,
+		None,
+		vec![did_signature(holder_key, did_document.as_bytes())],
+	)
+	.ok();
+	DIDModule::create_vc_fingerprint(
+		Origin::signed(1),
+		holder_key.public().encode(),
+		vc_hash,
+		Some(true),
+		None,
+		None,
+		None,
+	)
+	.ok();
+}
+
+#[rstest]
+fn anchor_presentation(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	vc_hash: Vec<u8>,
+	presentation_hash: Vec<u8>,
+) {
+	let holder_did = "did:trackback:holder".as_bytes().to_vec();
+	new_test_ext().execute_with(|| {
+		setup_presentation(
+			did_document,
+			did_uri.clone(),
+			holder_did.clone(),
+			&key_pair,
+			vc_hash.clone(),
+		);
+
+		assert_ok!(DIDModule::anchor_presentation(
+			Origin::signed(1),
+			presentation_hash.clone(),
+			vc_hash,
+			did_uri,
+			holder_did.clone(),
+			vec![did_signature(&key_pair, &presentation_hash)]
+		));
+		assert_eq!(
+			DIDModule::get_presentation(presentation_hash).map(|p| p.holder_did),
+			Some(holder_did)
+		);
+	});
+}
+
+#[rstest]
+fn anchor_presentation_without_holder_consent(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	vc_hash: Vec<u8>,
+	presentation_hash: Vec<u8>,
+) {
+	let holder_did = "did:trackback:holder".as_bytes().to_vec();
+	new_test_ext().execute_with(|| {
+		setup_presentation(
+			did_document,
+			did_uri.clone(),
+			holder_did.clone(),
+			&key_pair,
+			vc_hash.clone(),
+		);
+
+		assert_err!(
+			DIDModule::anchor_presentation(
+				Origin::signed(1),
+				presentation_hash.clone(),
+				vc_hash.clone(),
+				did_uri.clone(),
+				holder_did.clone(),
+				vec![]
+			),
+			DispatchError::Module {
+				index: 1,
+				error: 14,
+				message: Some("PresentationConsentMissing")
+			}
+		);
+
+		// Consent signed by a key which does not belong to the holder's DID
+		assert_err!(
+			DIDModule::anchor_presentation(
+				Origin::signed(1),
+				presentation_hash.clone(),
+				vc_hash,
+				did_uri,
+				holder_did,
+				vec![did_signature(&ed25519::Pair::generate().0, &presentation_hash)]
+			),
+			DispatchError::Module { index: 1, error: 15, message: Some("ConsentKeyNotFound") }
+		);
+	});
+}
+
+#[rstest]
+fn anchor_presentation_with_invalid_consent(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	vc_hash: Vec<u8>,
+	presentation_hash: Vec<u8>,
+) {
+	let holder_did = "did:trackback:holder".as_bytes().to_vec();
+	new_test_ext().execute_with(|| {
+		setup_presentation(
+			did_document,
+			did_uri.clone(),
+			holder_did.clone(),
+			&key_pair,
+			vc_hash.clone(),
+		);
+
+		assert_err!(
+			DIDModule::anchor_presentation(
+				Origin::signed(1),
+				presentation_hash,
+				vc_hash,
+				did_uri,
+				holder_did,
+				vec![did_signature(&key_pair, "another presentation".as_bytes())]
+			),
+			DispatchError::Module {
+				index: 1,
+				error: 5,
+				message: Some("DIDProofVerificationFailed")
+			}
+		);
+	});
+}
+
+#[rstest]
+fn anchor_presentation_by_another_verifier(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	vc_hash: Vec<u8>,
+	presentation_hash: Vec<u8>,
+) {
+	let holder_did = "did:trackback:holder".as_bytes().to_vec();
+	new_test_ext().execute_with(|| {
+		setup_presentation(
+			did_document,
+			did_uri.clone(),
+			holder_did.clone(),
+			&key_pair,
+			vc_hash.clone(),
+		);
+
+		assert_err!(
+			DIDModule::anchor_presentation(
+				Origin::signed(2),
+				presentation_hash.clone(),
+				vc_hash,
+				did_uri,
+				holder_did,
+				vec![did_signature(&key_pair, &presentation_hash)]
+			),
+			DispatchError::Module { index: 1, error: 11, message: Some("NotDIDController") }
+		);
+	});
+}
+
+#[fixture]
+pub fn vc_hashes() -> Vec<Vec<u8>> {
+	(0..5u8).map(|i| Blake2Hasher::hash(&[i]).as_bytes().to_vec()).collect()
+}
+
+#[rstest]
+fn merkle_proofs_verify_every_credential(vc_hashes: Vec<Vec<u8>>) {
+	let root = merkle::root(&vc_hashes).unwrap();
+
+	for (i, vc_hash) in vc_hashes.iter().enumerate() {
+		let proof: Vec<Vec<u8>> =
+			merkle::proof(&vc_hashes, i).unwrap().iter().map(|p| p.to_vec()).collect();
+		assert!(merkle::verify(&root, vc_hash, &proof, 5));
+		assert!(!merkle::verify(&root, "Hash".as_bytes(), &proof, 5));
+	}
+	assert_eq!(merkle::proof(&vc_hashes, vc_hashes.len()), None);
+	assert_eq!(merkle::root(&[]), None);
+}
+
+#[rstest]
+fn merkle_proofs_are_bound_to_their_tree(vc_hashes: Vec<Vec<u8>>) {
+	let root = merkle::root(&vc_hashes).unwrap();
+	let proof: Vec<Vec<u8>> =
+		merkle::proof(&vc_hashes, 0).unwrap().iter().map(|p| p.to_vec()).collect();
+	assert_eq!(proof.len(), merkle::max_proof_len(5));
+
+	// Longer than the height of the anchored tree
+	assert!(!merkle::verify(&root, &vc_hashes[0], &proof, 4));
+
+	// Inner nodes are not leaves
+	let node = merkle::parent(
+		&merkle::leaf(&vc_hashes[0]).unwrap(),
+		&merkle::leaf(&vc_hashes[1]).unwrap(),
+	);
+	assert!(!merkle::verify(&root, &node, &proof[1..], 5));
+
+	// Credential hashes are 32 bytes
+	let short: Vec<Vec<u8>> = vc_hashes.iter().map(|h| h[..31].to_vec()).collect();
+	assert_eq!(merkle::root(&short), None);
+	assert!(!merkle::verify(&root, &vc_hashes[0][..31], &proof, 5));
+
+	assert_eq!(
+		(1..=9).map(merkle::max_proof_len).collect::<Vec<_>>(),
+		vec![0, 1, 2, 2, 3, 3, 3, 3, 4]
+	);
+}
+
+#[rstest]
+fn anchor_vc_batch(vc_hashes: Vec<Vec<u8>>) {
+	let root = merkle::root(&vc_hashes).unwrap().to_vec();
+	let proof: Vec<Vec<u8>> =
+		merkle::proof(&vc_hashes, 3).unwrap().iter().map(|p| p.to_vec()).collect();
+
+	new_test_ext().execute_with(|| {
+		assert_ok!(DIDModule::anchor_vc_batch(Origin::signed(1), root.clone(), 5, None, None));
+
+		let batch =
+			DIDModule::verify_batch_inclusion(root.clone(), vc_hashes[3].clone(), proof.clone());
+		assert_eq!(batch.map(|b| (b.issuer, b.leaf_count)), Some((1, 5)));
+
+		// The proof does not include another credential
+		assert!(DIDModule::verify_batch_inclusion(root, vc_hashes[2].clone(), proof).is_none());
+	});
+}
+
+#[rstest]
+fn anchor_an_existing_vc_batch(vc_hashes: Vec<Vec<u8>>) {
+	let root = merkle::root(&vc_hashes).unwrap().to_vec();
+
+	new_test_ext().execute_with(|| {
+		DIDModule::anchor_vc_batch(Origin::signed(1), root.clone(), 5, None, None).ok();
+
+		assert_err!(
+			DIDModule::anchor_vc_batch(Origin::signed(1), root, 5, None, None),
+			DispatchError::Module {
+				index: 1,
+				error: 16,
+				message: Some("VerifiableCredentialBatchExists")
+			}
+		);
+	});
+}
+
+#[rstest]
+fn anchor_an_invalid_vc_batch(vc_hash: Vec<u8>) {
+	new_test_ext().execute_with(|| {
+		assert_err!(
+			DIDModule::anchor_vc_batch(Origin::signed(1), vc_hash, 5, None, None),
+			DispatchError::Module {
+				index: 1,
+				error: 17,
+				message: Some("InvalidVerifiableCredentialBatch")
+			}
+		);
+	});
+}
+
+#[rstest]
+fn index_dids_by_controller_and_public_key(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	public_key: Vec<u8>,
+) {
+	new_test_ext().execute_with(|| {
+		assert_ok!(DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			None,
+			None,
+			public_key,
+			did_uri.clone(),
+			None,
+			vec![did_signature(&key_pair, did_document.as_bytes())]
+		));
+
+		assert_eq!(DIDModule::get_did_controller(&did_uri), Some(1));
+		assert_eq!(DIDModule::dids_by_controller(1), vec![did_uri.clone()]);
+		assert_eq!(DIDModule::dids_by_controller(2), Vec::<Vec<u8>>::new());
+		assert_eq!(DIDModule::dids_by_public_key(key_pair.public().encode()), vec![did_uri]);
+	});
+}
+
+#[rstest]
+fn reindex_public_keys_on_update(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	public_key: Vec<u8>,
+) {
+	let rotated_key = ed25519::Pair::generate().0;
+
+	new_test_ext().execute_with(|| {
+		DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			None,
+			None,
+			public_key,
+			did_uri.clone(),
+			None,
+			vec![did_signature(&key_pair, did_document.as_bytes())],
+		)
+		.ok();
+
+		assert_ok!(DIDModule::update_did(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			did_uri.clone(),
+			None,
+			None,
+			None,
+			vec![did_signature(&rotated_key, did_document.as_bytes())]
+		));
+
+		assert_eq!(
+			DIDModule::dids_by_public_key(key_pair.public().encode()),
+			Vec::<Vec<u8>>::new()
+		);
+		assert_eq!(DIDModule::dids_by_public_key(rotated_key.public().encode()), vec![did_uri]);
+	});
+}
+
+#[rstest]
+fn update_did_with_invalid_proofs_keeps_its_keys(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	public_key: Vec<u8>,
+) {
+	let valid = did_signature(&ed25519::Pair::generate().0, did_document.as_bytes());
+	let mut malformed = valid.clone();
+	malformed.public_key = vec![1, 2, 3];
+
+	new_test_ext().execute_with(|| {
+		DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			None,
+			None,
+			public_key,
+			did_uri.clone(),
+			None,
+			vec![did_signature(&key_pair, did_document.as_bytes())],
+		)
+		.ok();
+
+		for signatures in vec![
+			vec![valid.clone(), did_signature(&key_pair, b"another document")],
+			vec![valid.clone(), malformed],
+		] {
+			assert_err!(
+				DIDModule::update_did(
+					Origin::signed(1),
+					did_document.as_bytes().to_vec(),
+					did_uri.clone(),
+					None,
+					None,
+					None,
+					signatures
+				),
+				DispatchError::Module {
+					index: 1,
+					error: 5,
+					message: Some("DIDProofVerificationFailed")
+				}
+			);
+		}
+
+		let keys: Vec<Vec<u8>> = DIDModule::get_signature(&did_uri)
+			.unwrap()
+			.into_iter()
+			.map(|signature| signature.public_key)
+			.collect();
+		assert_eq!(keys, vec![key_pair.public().encode()]);
+		assert_eq!(DIDModule::dids_by_public_key(key_pair.public().encode()), vec![did_uri]);
+	});
+}
+
+#[rstest]
+fn unindex_dids_on_revoke(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	public_key: Vec<u8>,
+) {
+	new_test_ext().execute_with(|| {
+		DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			None,
+			None,
+			public_key,
+			did_uri.clone(),
+			None,
+			vec![did_signature(&key_pair, did_document.as_bytes())],
+		)
+		.ok();
+
+		assert_ok!(DIDModule::revoke_did(Origin::signed(1), did_uri.clone(), None));
+
+		assert_eq!(DIDModule::get_did_controller(&did_uri), None);
+		assert_eq!(DIDModule::dids_by_controller(1), Vec::<Vec<u8>>::new());
+		assert_eq!(
+			DIDModule::dids_by_public_key(key_pair.public().encode()),
+			Vec::<Vec<u8>>::new()
+		);
+	});
+}
+
+/// Registers offchain and transaction pool extensions, configures the IPFS gateway and holds the
+/// key of authority 1
+fn offchain_test_ext(
+) -> (sp_io::TestExternalities, Arc<RwLock<OffchainState>>, Arc<RwLock<PoolState>>) {
+	UintAuthorityId::set_all_keys(vec![1u64]);
+	let (offchain, offchain_state) = TestOffchainExt::new();
+	let (pool, pool_state) = TestTransactionPoolExt::new();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.execute_with(|| {
+		sp_io::offchain::local_storage_set(
+			StorageKind::PERSISTENT,
+			offchain::DID_REF_ENDPOINT,
+			b"http://localhost:8080/",
+		);
+	});
+
+	(t, offchain_state, pool_state)
+}
+
+/// Expects the offchain worker to fetch the document referenced by `did_ref`
+fn expect_did_ref_request(state: &Arc<RwLock<OffchainState>>, did_ref: &[u8], body: &[u8]) {
+	state.write().expect_request(PendingRequest {
+		method: "GET".into(),
+		uri: format!("http://localhost:8080/ipfs/{}", std::str::from_utf8(did_ref).unwrap()),
+		response: Some(body.to_vec()),
+		sent: true,
+		..Default::default()
+	});
+}
+
+#[rstest]
+fn offchain_worker_reports_did_ref_mismatch(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	did_ref: Option<Vec<u8>>,
+	public_key: Vec<u8>,
+) {
+	let (mut t, offchain_state, pool_state) = offchain_test_ext();
+	let tampered = b"tampered document";
+	expect_did_ref_request(&offchain_state, did_ref.as_ref().unwrap(), tampered);
+
+	t.execute_with(|| {
+		DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			None,
+			None,
+			public_key,
+			did_uri.clone(),
+			did_ref,
+			vec![did_signature(&key_pair, did_document.as_bytes())],
+		)
+		.ok();
+
+		DIDModule::offchain_worker(1);
+
+		let tx = pool_state.write().transactions.pop().unwrap();
+		assert!(pool_state.read().transactions.is_empty());
+		let tx = Extrinsic::decode(&mut &*tx).unwrap();
+		assert_eq!(tx.signature, None);
+		let (report, signature) = did_ref_report(&did_uri, 1, blake2_256(tampered).to_vec(), 1);
+		assert_eq!(
+			tx.call,
+			Call::DIDModule(crate::Call::report_did_ref_mismatch(report, signature))
+		);
+	});
+}
+
+#[test]
+fn offchain_worker_resumes_after_the_last_checked_did() {
+	let (mut t, _, pool_state) = offchain_test_ext();
+
+	t.execute_with(|| {
+		// DIDs without a document hash are skipped without fetching their document
+		for n in 0..7u8 {
+			crate::pallet::DIDDocument::<Test>::insert(
+				vec![n],
+				DID {
+					did_resolution_metadata: None,
+					did_document_metadata: None,
+					block_time_stamp: 0,
+					updated_time_stamp: 0,
+					did_ref: None,
+					sender_account_id: Vec::new(),
+					did_document_hash: Vec::new(),
+					version: 1,
+				},
+			);
+		}
+		let did_uris: Vec<Vec<u8>> =
+			crate::pallet::DIDDocument::<Test>::iter().map(|(did_uri, _)| did_uri).collect();
+
+		DIDModule::offchain_worker(1);
+		assert_eq!(
+			offchain::cursor(),
+			crate::pallet::DIDDocument::<Test>::hashed_key_for(
+				&did_uris[offchain::DID_REFS_PER_RUN - 1]
+			)
+		);
+
+		// The last two DIDs are checked and the next run starts over
+		DIDModule::offchain_worker(2);
+		assert!(offchain::cursor().is_empty());
+		assert!(pool_state.read().transactions.is_empty());
+	});
+}
+
+#[rstest]
+fn offchain_worker_fetches_ipfs_uris_from_the_gateway(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	did_ref: Option<Vec<u8>>,
+	public_key: Vec<u8>,
+) {
+	let (mut t, offchain_state, pool_state) = offchain_test_ext();
+	expect_did_ref_request(&offchain_state, did_ref.as_ref().unwrap(), did_document.as_bytes());
+
+	t.execute_with(|| {
+		DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			None,
+			None,
+			public_key,
+			did_uri,
+			Some([&b"ipfs://"[..], &did_ref.unwrap()].concat()),
+			vec![did_signature(&key_pair, did_document.as_bytes())],
+		)
+		.ok();
+
+		DIDModule::offchain_worker(1);
+
+		assert!(pool_state.read().transactions.is_empty());
+	});
+}
+
+#[rstest]
+fn offchain_worker_needs_an_authority_key(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	did_ref: Option<Vec<u8>>,
+	public_key: Vec<u8>,
+) {
+	let (mut t, _, pool_state) = offchain_test_ext();
+	UintAuthorityId::set_all_keys(vec![3u64]);
+
+	t.execute_with(|| {
+		DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			None,
+			None,
+			public_key,
+			did_uri,
+			did_ref,
+			vec![did_signature(&key_pair, did_document.as_bytes())],
+		)
+		.ok();
+
+		// No document is requested
+		DIDModule::offchain_worker(1);
+
+		assert!(pool_state.read().transactions.is_empty());
+	});
+}
+
+#[rstest]
+fn offchain_worker_skips_matching_did_ref(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	did_ref: Option<Vec<u8>>,
+	public_key: Vec<u8>,
+) {
+	let (mut t, offchain_state, pool_state) = offchain_test_ext();
+	expect_did_ref_request(&offchain_state, did_ref.as_ref().unwrap(), did_document.as_bytes());
+
+	t.execute_with(|| {
+		DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			None,
+			None,
+			public_key,
+			did_uri,
+			did_ref,
+			vec![did_signature(&key_pair, did_document.as_bytes())],
+		)
+		.ok();
+
+		DIDModule::offchain_worker(1);
+
+		assert!(pool_state.read().transactions.is_empty());
+	});
+}
+
+/// Builds a DID reference mismatch report signed by `authority`
+fn did_ref_report(
+	did_uri: &[u8],
+	version: u32,
+	fetched_hash: Vec<u8>,
+	authority: u64,
+) -> (DIDRefReport<UintAuthorityId>, TestSignature) {
+	let report = DIDRefReport {
+		did_uri: did_uri.to_vec(),
+		version,
+		fetched_hash,
+		authority: UintAuthorityId(authority),
+	};
+	let signature = report.using_encoded(|payload| UintAuthorityId(authority).sign(&payload));
+	(report, signature.unwrap())
+}
+
+#[rstest]
+fn report_did_ref_mismatch(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	public_key: Vec<u8>,
+) {
+	let fetched_hash = blake2_256(b"tampered document").to_vec();
+
+	new_test_ext().execute_with(|| {
+		DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			None,
+			None,
+			public_key,
+			did_uri.clone(),
+			None,
+			vec![did_signature(&key_pair, did_document.as_bytes())],
+		)
+		.ok();
+
+		let (report, signature) =
+			did_ref_report(&did_uri, 1, blake2_256(did_document.as_bytes()).to_vec(), 1);
+		assert_err!(
+			DIDModule::report_did_ref_mismatch(Origin::none(), report, signature),
+			DispatchError::Module { index: 1, error: 18, message: Some("DIDRefMatches") }
+		);
+		let (report, signature) = did_ref_report(&did_uri, 1, fetched_hash.clone(), 1);
+		assert_ok!(DIDModule::report_did_ref_mismatch(Origin::none(), report, signature));
+		assert_eq!(DIDModule::get_did_ref_mismatch(&did_uri), Some(fetched_hash.clone()));
+
+		assert_ok!(DIDModule::update_did(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			did_uri.clone(),
+			None,
+			None,
+			None,
+			vec![did_signature(&key_pair, did_document.as_bytes())]
+		));
+		assert_eq!(DIDModule::get_did_ref_mismatch(&did_uri), None);
+
+		// Reports of the previous version are not replayed
+		let (report, signature) = did_ref_report(&did_uri, 1, fetched_hash, 1);
+		assert_err!(
+			DIDModule::report_did_ref_mismatch(Origin::none(), report, signature),
+			DispatchError::Module { index: 1, error: 25, message: Some("DIDRefReportOutdated") }
+		);
+	});
+}
+
+#[rstest]
+fn reject_did_ref_reports_not_signed_by_an_authority(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	public_key: Vec<u8>,
+) {
+	let fetched_hash = blake2_256(b"tampered document").to_vec();
+	let validate = |(report, signature): (DIDRefReport<UintAuthorityId>, TestSignature)| {
+		DIDModule::validate_unsigned(
+			TransactionSource::External,
+			&crate::Call::report_did_ref_mismatch(report, signature),
+		)
+	};
+
+	new_test_ext().execute_with(|| {
+		DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			None,
+			None,
+			public_key,
+			did_uri.clone(),
+			None,
+			vec![did_signature(&key_pair, did_document.as_bytes())],
+		)
+		.ok();
+
+		// Reports of authorities propagate from any source
+		assert!(validate(did_ref_report(&did_uri, 1, fetched_hash.clone(), 2)).is_ok());
+
+		assert_eq!(
+			validate(did_ref_report(&did_uri, 1, fetched_hash.clone(), 3)),
+			Err(InvalidTransaction::BadSigner.into())
+		);
+
+		// Signed by another authority
+		let (report, _) = did_ref_report(&did_uri, 1, fetched_hash.clone(), 1);
+		let (_, signature) = did_ref_report(&did_uri, 1, fetched_hash.clone(), 2);
+		assert_eq!(validate((report, signature)), Err(InvalidTransaction::BadProof.into()));
+
+		assert_eq!(
+			validate(did_ref_report(&did_uri, 2, fetched_hash, 1)),
+			Err(InvalidTransaction::Stale.into())
+		);
+	});
+}
+
+#[rstest]
+#[case("QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o")]
+#[case("ipfs://QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o")]
+#[case("bafkreifjjcie6lypi6ny7amxnfftagclbuxndqonfipmb64f2km2devei4")]
+#[case("bafk2bzaceddrwbp5duohx57jfd7rrzmnwumt5eywifwme25jzsijjwua24ar4")]
+#[case("bafybeicg2rebjoofv4kbyovkw7af3rpiitvnl6i7ckcywaq6xjcxnc2mby")]
+fn cids_address_documents(#[case] cid: &'static str) {
+	let cid = Cid::parse(cid.as_bytes()).unwrap();
+
+	assert!(cid.matches(b"hello world\n"));
+	assert!(!cid.matches(b"hello world"));
+}
+
+#[rstest]
+#[case("")]
+#[case("QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff50")]
+#[case("{QmcNYMJBhvbrH8oTo5QGNUFA5rhKpBVXHBpfiecxso7D8P}")]
+#[case("bafkreifjjcie6lypi6ny7amxnfftagclbuxndqonfipmb64f2km2deve")]
+fn rejects_unsupported_cids(#[case] cid: &'static str) {
+	assert_eq!(Cid::parse(cid.as_bytes()), None);
+}
+
+#[rstest]
+#[case("{QmcNYMJBhvbrH8oTo5QGNUFA5rhKpBVXHBpfiecxso7D8P}", 19, "InvalidDIDRef")]
+#[case("QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o", 20, "DIDRefMismatch")]
+fn create_did_with_invalid_did_ref(
+	#[case] did_ref: &'static str,
+	#[case] error_num: u8,
+	#[case] message: &'static str,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	public_key: Vec<u8>,
+	signature: Vec<DIDSignature>,
+) {
+	new_test_ext().execute_with(|| {
+		assert_err!(
+			DIDModule::insert_did_document(
+				Origin::signed(1),
+				did_document.as_bytes().to_vec(),
+				None,
+				None,
+				public_key,
+				did_uri,
+				Some(did_ref.as_bytes().to_vec()),
+				signature
+			),
+			DispatchError::Module { index: 1, error: error_num, message: Some(message) }
+		);
+	});
+}
+
+#[rstest]
+fn update_did_increments_version(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	did_ref: Option<Vec<u8>>,
+	public_key: Vec<u8>,
+) {
+	new_test_ext().execute_with(|| {
+		DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			None,
+			None,
+			public_key,
+			did_uri.clone(),
+			did_ref.clone(),
+			vec![did_signature(&key_pair, did_document.as_bytes())],
+		)
+		.ok();
+		assert_eq!(DIDModule::get_did_document(&did_uri).unwrap().version, 1);
+
+		assert_ok!(DIDModule::update_did(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			did_uri.clone(),
+			None,
+			None,
+			did_ref,
+			vec![did_signature(&key_pair, did_document.as_bytes())]
+		));
+		assert_eq!(DIDModule::get_did_document(&did_uri).unwrap().version, 2);
+		assert_ne!(offchain::document_key(&did_uri, 1), offchain::document_key(&did_uri, 2));
+	});
+}
+
+#[rstest]
+fn did_events_carry_did_state(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	public_key: Vec<u8>,
+) {
+	let rotated_key = ed25519::Pair::generate().0;
+	let key = key_pair.public().encode();
+	let rotated = rotated_key.public().encode();
+	let document_hash = blake2_256(did_document.as_bytes()).to_vec();
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		assert_ok!(DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			None,
+			None,
+			public_key,
+			did_uri.clone(),
+			None,
+			vec![did_signature(&key_pair, did_document.as_bytes())]
+		));
+		assert_ok!(DIDModule::update_did(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			did_uri.clone(),
+			None,
+			None,
+			None,
+			vec![did_signature(&rotated_key, did_document.as_bytes())]
+		));
+		assert_ok!(DIDModule::revoke_did(
+			Origin::signed(1),
+			did_uri.clone(),
+			Some("compromised".as_bytes().to_vec())
+		));
+
+		let events: Vec<crate::Event<Test>> = System::events()
+			.into_iter()
+			.filter_map(|record| match record.event {
+				| Event::pallet_dids(event) => Some(event),
+				| _ => None,
+			})
+			.collect();
+
+		assert_eq!(
+			events,
+			vec![
+				crate::Event::DIDKeyAdded(did_uri.clone(), key.clone()),
+				crate::Event::DIDDocumentCreated(
+					did_uri.clone(),
+					1,
+					1,
+					document_hash.clone(),
+					vec![key.clone()],
+					0
+				),
+				crate::Event::DIDKeyRemoved(did_uri.clone(), key),
+				crate::Event::DIDKeyAdded(did_uri.clone(), rotated.clone()),
+				crate::Event::DIDDocumentUpdated(
+					did_uri.clone(),
+					1,
+					2,
+					document_hash,
+					vec![rotated.clone()],
+					0
+				),
+				crate::Event::DIDKeyRemoved(did_uri.clone(), rotated),
+				crate::Event::DIDDocumentRevoked(
+					did_uri,
+					1,
+					Some("compromised".as_bytes().to_vec()),
+					0
+				),
+			]
+		);
+	});
+}
+
+#[rstest]
+fn migrate_first_release_storage(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	did_ref: Option<Vec<u8>>,
+	vc_hash: Vec<u8>,
+) {
+	let key = key_pair.public().encode();
+	let hashed = |key: &Vec<u8>| Blake2_128Concat::hash(&key.encode());
+
+	new_test_ext().execute_with(|| {
+		put_storage_value(
+			b"DIDModule",
+			b"DIDDocument",
+			&hashed(&did_uri),
+			OldDID {
+				did_resolution_metadata: None,
+				did_document_metadata: None,
+				block_time_stamp: 1,
+				updated_time_stamp: 2,
+				did_ref: did_ref.clone(),
+				sender_account_id: 1u64.encode(),
+			},
+		);
+		put_storage_value(
+			b"DIDModule",
+			b"DIDProof",
+			&hashed(&did_uri),
+			vec![did_signature(&key_pair, did_document.as_bytes())],
+		);
+		put_storage_value(
+			b"DIDModule",
+			b"VC",
+			&hashed(&vc_hash),
+			OldVerifiableCredential::<u64> {
+				account_id: Some(2),
+				public_key: key.clone(),
+				block_time_stamp: 3,
+				active: Some(true),
+			},
+		);
+		put_storage_value(b"DIDModule", b"DIDs", &[1u8; 16], vec![0u8]);
+		// Trailing bytes after an account do not make a controller
+		let unknown_sender = b"did:trackback:unknown".to_vec();
+		put_storage_value(
+			b"DIDModule",
+			b"DIDDocument",
+			&hashed(&unknown_sender),
+			OldDID {
+				did_resolution_metadata: None,
+				did_document_metadata: None,
+				block_time_stamp: 1,
+				updated_time_stamp: 2,
+				did_ref: None,
+				sender_account_id: [1u64.encode(), vec![0u8]].concat(),
+			},
+		);
+
+		assert_eq!(DIDModule::pallet_storage_version(), Releases::V1_0_0);
+		DIDModule::on_runtime_upgrade();
+		assert_eq!(DIDModule::pallet_storage_version(), Releases::V2_0_0);
+
+		let did = DIDModule::get_did_document(&did_uri).unwrap();
+		assert_eq!((did.block_time_stamp, did.updated_time_stamp, did.version), (1, 2, 1));
+		assert_eq!(did.did_ref, did_ref);
+		assert!(did.did_document_hash.is_empty());
+
+		let vc = DIDModule::get_verifiable_credential_hash(&vc_hash).unwrap();
+		assert_eq!((vc.account_id, vc.public_key, vc.active), (Some(2), key.clone(), Some(true)));
+		assert_eq!(vc.issuer, None);
+
+		assert_eq!(DIDModule::get_did_controller(&did_uri), Some(1));
+		assert_eq!(DIDModule::dids_by_public_key(key), vec![did_uri.clone()]);
+		assert!(DIDModule::get_did_document(&unknown_sender).is_some());
+		assert_eq!(DIDModule::get_did_controller(&unknown_sender), None);
+		assert_eq!(DIDModule::dids_by_controller(1), vec![did_uri]);
+		assert!(!have_storage_value(b"DIDModule", b"DIDs", &[1u8; 16]));
+
+		// Migrations run once
+		assert_eq!(DIDModule::on_runtime_upgrade(), 0);
+	});
+}
+
+#[rstest]
+fn assign_controllers_to_dids_without_one(
+	did_document_metadata: Option<Vec<u8>>,
+	did_resolution_metadata: Option<Vec<u8>>,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	did_ref: Option<Vec<u8>>,
+	public_key: Vec<u8>,
+	signature: Vec<DIDSignature>,
+) {
+	new_test_ext().execute_with(|| {
+		assert_err!(
+			DIDModule::assign_did_controller(Origin::root(), did_uri.clone(), 2),
+			DispatchError::Module { index: 1, error: 1, message: Some("DIDDoesNotExists") }
+		);
+
+		DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			did_document_metadata,
+			did_resolution_metadata,
+			public_key,
+			did_uri.clone(),
+			did_ref,
+			signature,
+		)
+		.ok();
+
+		assert_err!(
+			DIDModule::assign_did_controller(Origin::root(), did_uri.clone(), 2),
+			DispatchError::Module { index: 1, error: 27, message: Some("DIDControllerExists") }
+		);
+
+		// A DID left without a controller, as by the migration
+		crate::pallet::DIDController::<Test>::remove(&did_uri);
+		crate::pallet::DIDsByController::<Test>::remove(1, &did_uri);
+
+		assert_err!(
+			DIDModule::assign_did_controller(Origin::signed(2), did_uri.clone(), 2),
+			BadOrigin
+		);
+		assert_ok!(DIDModule::assign_did_controller(Origin::root(), did_uri.clone(), 2));
+		assert_eq!(DIDModule::get_did_controller(&did_uri), Some(2));
+		assert_eq!(DIDModule::dids_by_controller(2), vec![did_uri.clone()]);
+		assert_ok!(DIDModule::revoke_did(Origin::signed(2), did_uri, None));
+	});
+}
+
+/// Builds the test externalities with seeded DIDs and verifiable credentials
+fn genesis_ext(
+	dids: Vec<GenesisDID<u64>>,
+	vcs: Vec<(Vec<u8>, VerifiableCredential<Test>)>,
+) -> sp_io::TestExternalities {
+	let mut storage = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	crate::GenesisConfig::<Test> { dids, vcs }
+		.assimilate_storage(&mut storage)
+		.unwrap();
+	storage.into()
+}
+
+#[fixture]
+pub fn genesis_did(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	did_ref: Option<Vec<u8>>,
+) -> GenesisDID<u64> {
+	GenesisDID {
+		did_uri,
+		did_document: did_document.as_bytes().to_vec(),
+		controller: 1,
+		did_ref,
+		did_document_metadata: None,
+		did_resolution_metadata: None,
+		signatures: vec![did_signature(&key_pair, did_document.as_bytes())],
+	}
+}
+
+#[rstest]
+fn seed_dids_and_credentials_at_genesis(
+	genesis_did: GenesisDID<u64>,
+	did_document: &'static str,
+	vc_hash: Vec<u8>,
+) {
+	let public_key = genesis_did.signatures[0].public_key.clone();
+	let did_uri = genesis_did.did_uri.clone();
+	let vc = VerifiableCredential::<Test> {
+		account_id: Some(1),
+		public_key: public_key.clone(),
+		block_time_stamp: 0,
+		active: Some(true),
+		schema_id: None,
+		issuer: None,
+		issuer_did: Some(did_uri.clone()),
+		credential_type: None,
+	};
+	let empty = GenesisDID { did_uri: vec![], ..genesis_did.clone() };
+
+	genesis_ext(
+		vec![genesis_did, empty],
+		vec![(vc_hash.clone(), vc), (vec![], Default::default())],
+	)
+	.execute_with(|| {
+		let did = DIDModule::get_did_document(&did_uri).unwrap();
+		assert_eq!(did.did_document_hash, blake2_256(did_document.as_bytes()).to_vec());
+		assert_eq!((did.version, did.sender_account_id), (1, 1u64.encode()));
+
+		assert_eq!(DIDModule::get_did_controller(&did_uri), Some(1));
+		assert_eq!(DIDModule::dids_by_controller(1), vec![did_uri.clone()]);
+		assert_eq!(DIDModule::dids_by_public_key(public_key), vec![did_uri]);
+		assert!(DIDModule::get_verifiable_credential_hash(&vc_hash).is_some());
+		assert_eq!(DIDModule::get_did_document(Vec::<u8>::new()), None);
+		assert_eq!(DIDModule::get_verifiable_credential_hash(Vec::<u8>::new()), None);
+		assert_eq!(DIDModule::pallet_storage_version(), Releases::V2_0_0);
+	});
+}
+
+#[rstest]
+#[should_panic(expected = "Genesis DID proofs must verify against their documents")]
+fn reject_genesis_dids_with_invalid_proofs(genesis_did: GenesisDID<u64>, key_pair: KeyPair) {
+	let signatures = vec![did_signature(&key_pair, b"another document")];
+	genesis_ext(vec![GenesisDID { signatures, ..genesis_did }], vec![]);
+}
+
+#[rstest]
+#[should_panic(expected = "Genesis DID references must address their documents")]
+fn reject_genesis_dids_with_mismatched_did_ref(genesis_did: GenesisDID<u64>) {
+	let did_ref = Some(b"QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o".to_vec());
+	genesis_ext(vec![GenesisDID { did_ref, ..genesis_did }], vec![]);
+}
+
+/// Submits a call on behalf of a DID, signed over `nonce` by `key_pair`
+fn submit_did_call(
+	did_uri: &[u8],
+	key_pair: &KeyPair,
+	public_key: Vec<u8>,
+	nonce: u64,
+	call: &Call,
+) -> crate::Call<Test> {
+	let signature = key_pair.sign(&DIDModule::did_call_payload(did_uri, nonce, call));
+	crate::Call::submit_did_call(
+		did_uri.to_vec(),
+		public_key,
+		nonce,
+		Box::new(call.clone()),
+		signature,
+	)
+}
+
+#[rstest]
+fn dispatch_calls_authorised_by_a_did(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	public_key: Vec<u8>,
+	credential_type: Vec<u8>,
+) {
+	let key = key_pair.public().encode();
+	let call =
+		Call::DIDModule(crate::Call::accredit_issuer(credential_type.clone(), did_uri.clone()));
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			None,
+			None,
+			public_key,
+			did_uri.clone(),
+			None,
+			vec![did_signature(&key_pair, did_document.as_bytes())]
+		));
+
+		let did_call = submit_did_call(&did_uri, &key_pair, key.clone(), 0, &call);
+		assert_ok!(Call::DIDModule(did_call.clone()).dispatch(Origin::signed(2)));
+		assert_eq!(DIDModule::trusted_issuers(credential_type), vec![did_uri.clone()]);
+		assert_eq!(DIDModule::did_nonce(&did_uri), 1);
+
+		// Calls can not be replayed
+		assert_err!(
+			Call::DIDModule(did_call).dispatch(Origin::signed(2)).map_err(|e| e.error),
+			DispatchError::Module { index: 1, error: 21, message: Some("InvalidDIDNonce") }
+		);
+
+		// Failing calls use up their nonce
+		let did_call = submit_did_call(&did_uri, &key_pair, key, 1, &call);
+		assert_ok!(Call::DIDModule(did_call).dispatch(Origin::signed(2)));
+		assert_eq!(DIDModule::did_nonce(&did_uri), 2);
+		assert_eq!(
+			System::events().pop().map(|record| record.event),
+			Some(Event::pallet_dids(crate::Event::DIDCallDispatched(
+				did_uri,
+				Err(DispatchError::Module {
+					index: 1,
+					error: 9,
+					message: Some("IssuerAlreadyAccredited")
+				})
+			)))
+		);
+	});
+}
+
+#[rstest]
+fn register_schemas_authored_by_a_did(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	public_key: Vec<u8>,
+	schema_hash: Vec<u8>,
+) {
+	let key = key_pair.public().encode();
+	let call = Call::DIDModule(crate::Call::register_did_schema(schema_hash.clone(), 1, None));
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			None,
+			None,
+			public_key,
+			did_uri.clone(),
+			None,
+			vec![did_signature(&key_pair, did_document.as_bytes())]
+		));
+
+		// Only DIDs author schemas with this call
+		assert_err!(
+			DIDModule::register_did_schema(Origin::signed(1), schema_hash.clone(), 1, None),
+			BadOrigin
+		);
+
+		let did_call = submit_did_call(&did_uri, &key_pair, key, 0, &call);
+		assert_ok!(Call::DIDModule(did_call).dispatch(Origin::signed(2)));
+		assert_eq!(DIDModule::get_schema(&schema_hash).map(|s| s.author), Some(did_uri.clone()));
+		assert_eq!(
+			System::events().pop().map(|record| record.event),
+			Some(Event::pallet_dids(crate::Event::DIDCallDispatched(did_uri, Ok(()))))
+		);
+	});
+}
+
+#[rstest]
+fn reject_calls_not_authorised_by_a_did(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	public_key: Vec<u8>,
+	credential_type: Vec<u8>,
+) {
+	let other_key_pair = ed25519::Pair::generate().0;
+	let key = key_pair.public().encode();
+	let call =
+		Call::DIDModule(crate::Call::accredit_issuer(credential_type.clone(), did_uri.clone()));
+	let dispatch = |did_call: crate::Call<Test>| {
+		Call::DIDModule(did_call).dispatch(Origin::signed(2)).map_err(|e| e.error)
+	};
+
+	new_test_ext().execute_with(|| {
+		assert_err!(
+			dispatch(submit_did_call(&did_uri, &key_pair, key.clone(), 0, &call)),
+			DispatchError::Module { index: 1, error: 1, message: Some("DIDDoesNotExists") }
+		);
+
+		assert_ok!(DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			None,
+			None,
+			public_key,
+			did_uri.clone(),
+			None,
+			vec![did_signature(&key_pair, did_document.as_bytes())]
+		));
+
+		assert_err!(
+			dispatch(submit_did_call(
+				&did_uri,
+				&other_key_pair,
+				other_key_pair.public().encode(),
+				0,
+				&call
+			)),
+			DispatchError::Module { index: 1, error: 6, message: Some("DIDProofNotFound") }
+		);
+		assert_err!(
+			dispatch(submit_did_call(&did_uri, &other_key_pair, key.clone(), 0, &call)),
+			DispatchError::Module {
+				index: 1,
+				error: 5,
+				message: Some("DIDProofVerificationFailed")
+			}
+		);
+		assert_eq!(DIDModule::did_nonce(&did_uri), 0);
+		assert!(DIDModule::trusted_issuers(credential_type).is_empty());
+
+		assert_eq!(
+			crate::ensure_did(Origin::from(crate::RawOrigin::DID(did_uri.clone()))),
+			Ok(did_uri)
+		);
+		assert_eq!(crate::ensure_did(Origin::signed(1)), Err(BadOrigin));
+	});
+}
+
+#[rstest]
+fn check_did_authorization_orders_did_calls(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	public_key: Vec<u8>,
+	credential_type: Vec<u8>,
+) {
+	let key = key_pair.public().encode();
+	let call =
+		Call::DIDModule(crate::Call::accredit_issuer(credential_type.clone(), did_uri.clone()));
+	let check = crate::CheckDidAuthorization::<Test>::new();
+	let validate = |did_call: crate::Call<Test>| {
+		check.validate(&2, &Call::DIDModule(did_call), &DispatchInfo::default(), 0)
+	};
+	let tag = |nonce: u64| (b"DIDCall", &did_uri, nonce).encode();
+
+	new_test_ext().execute_with(|| {
+		assert_ok!(DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			None,
+			None,
+			public_key,
+			did_uri.clone(),
+			None,
+			vec![did_signature(&key_pair, did_document.as_bytes())]
+		));
+
+		assert_eq!(
+			validate(submit_did_call(&did_uri, &key_pair, key.clone(), 0, &call)),
+			Ok(ValidTransaction { provides: vec![tag(0)], ..Default::default() })
+		);
+		assert_eq!(
+			validate(submit_did_call(&did_uri, &key_pair, key.clone(), 1, &call)),
+			Ok(ValidTransaction {
+				requires: vec![tag(0)],
+				provides: vec![tag(1)],
+				..Default::default()
+			})
+		);
+
+		let mut did_call = submit_did_call(&did_uri, &key_pair, key.clone(), 0, &call);
+		if let crate::Call::submit_did_call(_, _, nonce, _, _) = &mut did_call {
+			*nonce = 1;
+		}
+		assert_eq!(validate(did_call), Err(InvalidTransaction::BadProof.into()));
+
+		let did_call = submit_did_call(&did_uri, &key_pair, key, 0, &call);
+		assert_ok!(Call::DIDModule(did_call.clone()).dispatch(Origin::signed(2)));
+		assert_eq!(validate(did_call), Err(InvalidTransaction::Stale.into()));
+
+		// Other calls are not checked
+		assert_eq!(
+			check.validate(&2, &call, &DispatchInfo::default(), 0),
+			Ok(ValidTransaction::default())
+		);
+	});
+}
+
+type FeeAdapter = crate::SponsoredCurrencyAdapter<()>;
+
+/// Withdraws `fee` for a call of `who` and corrects it to `corrected_fee` after dispatch
+fn charge_fee(who: u64, call: &Call, fee: u64, corrected_fee: u64) {
+	let info = call.get_dispatch_info();
+	let paid =
+		<FeeAdapter as OnChargeTransaction<Test>>::withdraw_fee(&who, call, &info, fee, 0).unwrap();
+	assert_ok!(<FeeAdapter as OnChargeTransaction<Test>>::correct_and_deposit_fee(
+		&who,
+		&info,
+		&PostDispatchInfo::from(()),
+		corrected_fee,
+		0,
+		paid
+	));
+}
+
+#[rstest]
+fn sponsor_fees_of_did_operations(did_uri: Vec<u8>) {
+	let did_call = Call::DIDModule(crate::Call::revoke_did(did_uri, None));
+	let transfer = Call::Balances(pallet_balances::Call::transfer(3, 10));
+
+	new_test_ext().execute_with(|| {
+		Balances::make_free_balance_be(&1, 1_000);
+		Balances::make_free_balance_be(&2, 100);
+
+		assert_ok!(DIDModule::sponsor_fees(Origin::signed(1), 2, 50));
+		assert_err!(
+			DIDModule::sponsor_fees(Origin::signed(3), 2, 50),
+			DispatchError::Module { index: 1, error: 22, message: Some("FeeSponsorExists") }
+		);
+
+		// The sponsor pays DID operations, unused fees return to the allowance
+		charge_fee(2, &did_call, 30, 20);
+		assert_eq!((Balances::free_balance(1), Balances::free_balance(2)), (980, 100));
+		assert_eq!(DIDModule::fee_sponsor(2), Some((1, 30)));
+
+		// The sender pays beyond the allowance and for other calls
+		charge_fee(2, &did_call, 40, 40);
+		charge_fee(2, &transfer, 10, 10);
+		assert_eq!((Balances::free_balance(1), Balances::free_balance(2)), (980, 50));
+		assert_eq!(DIDModule::fee_sponsor(2), Some((1, 30)));
+	});
+}
+
+#[rstest]
+fn sponsor_only_did_operations(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	public_key: Vec<u8>,
+) {
+	let insert = Call::DIDModule(crate::Call::insert_did_document(
+		did_document.as_bytes().to_vec(),
+		None,
+		None,
+		public_key,
+		did_uri.clone(),
+		None,
+		vec![did_signature(&key_pair, did_document.as_bytes())],
+	));
+	let revoke = Call::DIDModule(crate::Call::revoke_did(did_uri, None));
+	let sponsor_fees = Call::DIDModule(crate::Call::sponsor_fees(3, 10));
+
+	new_test_ext().execute_with(|| {
+		Balances::make_free_balance_be(&1, 1_000);
+		Balances::make_free_balance_be(&2, 100);
+		assert_ok!(DIDModule::sponsor_fees(Origin::signed(1), 2, 50));
+
+		// Other calls of the pallet, and tips of feeless DID operations
+		assert_eq!(insert.get_dispatch_info().pays_fee, Pays::No);
+		charge_fee(2, &sponsor_fees, 10, 10);
+		charge_fee(2, &insert, 5, 5);
+		assert_eq!((Balances::free_balance(1), Balances::free_balance(2)), (1_000, 85));
+		assert_eq!(DIDModule::fee_sponsor(2), Some((1, 50)));
+
+		charge_fee(2, &revoke, 10, 10);
+		assert_eq!((Balances::free_balance(1), Balances::free_balance(2)), (990, 85));
+		assert_eq!(DIDModule::fee_sponsor(2), Some((1, 40)));
+	});
+}
+
+#[rstest]
+fn sender_pays_when_the_sponsor_can_not(did_uri: Vec<u8>) {
+	let revoke = Call::DIDModule(crate::Call::revoke_did(did_uri, None));
+
+	new_test_ext().execute_with(|| {
+		Balances::make_free_balance_be(&1, 5);
+		Balances::make_free_balance_be(&2, 100);
+		assert_ok!(DIDModule::sponsor_fees(Origin::signed(1), 2, 50));
+
+		charge_fee(2, &revoke, 10, 10);
+		assert_eq!((Balances::free_balance(1), Balances::free_balance(2)), (5, 90));
+		assert_eq!(DIDModule::fee_sponsor(2), Some((1, 50)));
+	});
+}
+
+#[rstest]
+fn revoke_fee_sponsorship() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(DIDModule::sponsor_fees(Origin::signed(1), 2, 50));
+		assert_err!(
+			DIDModule::revoke_fee_sponsorship(Origin::signed(3), 2),
+			DispatchError::Module { index: 1, error: 24, message: Some("NotFeeSponsor") }
+		);
+
+		assert_ok!(DIDModule::revoke_fee_sponsorship(Origin::signed(2), 2));
+		assert_eq!(DIDModule::fee_sponsor(2), None);
+		assert_err!(
+			DIDModule::revoke_fee_sponsorship(Origin::signed(1), 2),
+			DispatchError::Module { index: 1, error: 23, message: Some("FeeSponsorNotFound") }
+		);
+	});
+}
+
+/// Runs a call through `CheckDidOperationLimits` as a signed extrinsic of `who` would be
+fn check_did_operation(who: u64, call: &Call) -> Result<(), TransactionValidityError> {
+	crate::CheckDidOperationLimits::<Test>::new().pre_dispatch(
+		&who,
+		call,
+		&call.get_dispatch_info(),
+		0,
+	)
+}
+
+#[rstest]
+fn feeless_did_operations_need_valid_proofs(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	public_key: Vec<u8>,
+) {
+	let insert = |signatures: Vec<DIDSignature>| {
+		Call::DIDModule(crate::Call::insert_did_document(
+			did_document.as_bytes().to_vec(),
+			None,
+			None,
+			public_key.clone(),
+			did_uri.clone(),
+			None,
+			signatures,
+		))
+	};
+	let update = Call::DIDModule(crate::Call::update_did(
+		did_document.as_bytes().to_vec(),
+		did_uri.clone(),
+		None,
+		None,
+		None,
+		vec![did_signature(&key_pair, did_document.as_bytes())],
+	));
+
+	new_test_ext().execute_with(|| {
+		let call = insert(vec![did_signature(&key_pair, did_document.as_bytes())]);
+		assert_eq!(call.get_dispatch_info().pays_fee, Pays::No);
+
+		assert_eq!(
+			check_did_operation(1, &insert(vec![])),
+			Err(InvalidTransaction::BadProof.into())
+		);
+		assert_eq!(
+			check_did_operation(1, &insert(vec![did_signature(&key_pair, b"another document")])),
+			Err(InvalidTransaction::BadProof.into())
+		);
+		assert_eq!(check_did_operation(1, &call), Ok(()));
+		assert_ok!(call.dispatch(Origin::signed(1)));
+
+		// Only the controller updates a DID for free
+		assert_eq!(check_did_operation(2, &update), Err(InvalidTransaction::BadProof.into()));
+		assert_eq!(check_did_operation(1, &update), Ok(()));
+	});
+}
+
+#[rstest]
+fn rate_limit_did_operations(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	public_key: Vec<u8>,
+) {
+	let insert = |did_uri: &[u8]| {
+		Call::DIDModule(crate::Call::insert_did_document(
+			did_document.as_bytes().to_vec(),
+			None,
+			None,
+			public_key.clone(),
+			did_uri.to_vec(),
+			None,
+			vec![did_signature(&key_pair, did_document.as_bytes())],
+		))
+	};
+	let exhausted = Err(InvalidTransaction::ExhaustsResources.into());
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		// Per DID
+		assert_eq!(check_did_operation(1, &insert(&did_uri)), Ok(()));
+		assert_eq!(check_did_operation(2, &insert(&did_uri)), Ok(()));
+		assert_eq!(check_did_operation(3, &insert(&did_uri)), exhausted);
+
+		// Per account
+		assert_eq!(check_did_operation(1, &insert(b"did:trackback:other")), Ok(()));
+		assert_eq!(check_did_operation(1, &insert(b"did:trackback:another")), exhausted);
+
+		// Other calls are not limited
+		let call = Call::DIDModule(crate::Call::revoke_did(did_uri.clone(), None));
+		assert_eq!(check_did_operation(1, &call), Ok(()));
+
+		// Limits reset every era
+		System::set_block_number(10);
+		assert_eq!(check_did_operation(1, &insert(&did_uri)), Ok(()));
+	});
+}
+
+/// Registers a DID without an account, signed by `key_pair` over `controller` and `nonce`
+fn insert_did_document_unsigned(
+	key_pair: &KeyPair,
+	did_document: &[u8],
+	did_uri: &[u8],
+	controller: u64,
+	nonce: u64,
+) -> crate::Call<Test> {
+	let payload = DIDModule::did_registration_payload(did_uri, did_document, &controller, nonce);
+	crate::Call::insert_did_document_unsigned(
+		did_document.to_vec(),
+		None,
+		None,
+		controller,
+		did_uri.to_vec(),
+		None,
+		vec![did_signature(key_pair, did_document)],
+		nonce,
+		key_pair.sign(&payload),
+	)
+}
+
+#[rstest]
+fn register_a_did_without_an_account(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+) {
+	let call = insert_did_document_unsigned(&key_pair, did_document.as_bytes(), &did_uri, 7, 0);
+
+	new_test_ext().execute_with(|| {
+		assert_eq!(
+			DIDModule::validate_unsigned(TransactionSource::External, &call),
+			Ok(ValidTransaction {
+				priority: 100,
+				requires: vec![],
+				provides: vec![crate::origin::did_nonce_tag(&did_uri, 0)],
+				longevity: 64,
+				propagate: true,
+			})
+		);
+
+		assert_ok!(Call::DIDModule(call.clone()).dispatch(Origin::none()));
+		assert_eq!(DIDModule::get_did_controller(&did_uri), Some(7));
+		assert_eq!(
+			DIDModule::dids_by_public_key(key_pair.public().encode()),
+			vec![did_uri.clone()]
+		);
+		assert_eq!(DIDModule::did_nonce(&did_uri), 1);
+
+		assert_eq!(
+			DIDModule::validate_unsigned(TransactionSource::External, &call),
+			Err(InvalidTransaction::Stale.into())
+		);
+
+		// Registrations of a revoked DID can not be replayed
+		assert_ok!(DIDModule::revoke_did(Origin::signed(7), did_uri.clone(), None));
+		assert_eq!(
+			DIDModule::validate_unsigned(TransactionSource::External, &call),
+			Err(InvalidTransaction::Stale.into())
+		);
+		assert_err!(
+			Call::DIDModule(call).dispatch(Origin::none()).map_err(|e| e.error),
+			DispatchError::Module { index: 1, error: 21, message: Some("InvalidDIDNonce") }
+		);
+	});
+}
+
+#[rstest]
+fn reject_unauthenticated_did_registrations(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+) {
+	let other_key_pair = ed25519::Pair::generate().0;
+	let mut call = insert_did_document_unsigned(&key_pair, did_document.as_bytes(), &did_uri, 7, 0);
+	// Controller swapped after signing
+	if let crate::Call::insert_did_document_unsigned(_, _, _, controller, ..) = &mut call {
+		*controller = 8;
+	}
+	let mut other_call =
+		insert_did_document_unsigned(&key_pair, did_document.as_bytes(), &did_uri, 7, 0);
+	// Registration signed by a key which is not a proof of the document
+	if let crate::Call::insert_did_document_unsigned(.., signature) = &mut other_call {
+		*signature = other_key_pair.sign(&DIDModule::did_registration_payload(
+			&did_uri,
+			did_document.as_bytes(),
+			&7,
+			0,
+		));
+	}
+
+	new_test_ext().execute_with(|| {
+		for call in vec![call, other_call] {
+			assert_eq!(
+				DIDModule::validate_unsigned(TransactionSource::External, &call),
+				Err(InvalidTransaction::BadProof.into())
+			);
+			assert_err!(
+				Call::DIDModule(call).dispatch(Origin::none()).map_err(|e| e.error),
+				DispatchError::Module {
+					index: 1,
+					error: 5,
+					message: Some("DIDProofVerificationFailed")
+				}
+			);
+		}
+		assert_eq!(DIDModule::get_did_document(&did_uri), None);
+	});
+}
+
+#[rstest]
+fn rate_limit_did_registrations_without_an_account(key_pair: KeyPair, did_document: &'static str) {
+	let register = |key_pair: &KeyPair, did_uri: &[u8]| {
+		insert_did_document_unsigned(key_pair, did_document.as_bytes(), did_uri, 7, 0)
+	};
+	let exhausted = Err(InvalidTransaction::ExhaustsResources.into());
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		for did_uri in vec![&b"did:trackback:1"[..], b"did:trackback:2"] {
+			assert_ok!(Call::DIDModule(register(&key_pair, did_uri)).dispatch(Origin::none()));
+		}
+
+		// Per key
+		let call = register(&key_pair, b"did:trackback:3");
+		assert_eq!(DIDModule::validate_unsigned(TransactionSource::External, &call), exhausted);
+
+		// Per block, whatever the keys
+		let call = register(&ed25519::Pair::generate().0, b"did:trackback:3");
+		assert!(DIDModule::validate_unsigned(TransactionSource::External, &call).is_ok());
+		assert_ok!(Call::DIDModule(call).dispatch(Origin::none()));
+		let fresh_key = ed25519::Pair::generate().0;
+		let call = register(&fresh_key, b"did:trackback:4");
+		assert_eq!(DIDModule::validate_unsigned(TransactionSource::External, &call), exhausted);
+
+		// Controllers, which do not sign registrations, are not limited
+		System::set_block_number(2);
+		assert!(DIDModule::validate_unsigned(TransactionSource::External, &call).is_ok());
+		assert_ok!(Call::DIDModule(call).dispatch(Origin::none()));
+		assert_eq!(DIDModule::dids_by_controller(7).len(), 4);
+
+		// Limits reset every era
+		System::set_block_number(10);
+		let call = register(&key_pair, b"did:trackback:5");
+		assert!(DIDModule::validate_unsigned(TransactionSource::External, &call).is_ok());
+	});
+}
+
+#[rstest]
+fn reject_oversized_did_registrations(key_pair: KeyPair, did_uri: Vec<u8>) {
+	let did_document = vec![b'a'; 4 * 1024];
+	let call = insert_did_document_unsigned(&key_pair, &did_document, &did_uri, 7, 0);
+
+	new_test_ext().execute_with(|| {
+		assert_eq!(
+			DIDModule::validate_unsigned(TransactionSource::External, &call),
+			Err(InvalidTransaction::ExhaustsResources.into())
+		);
+	});
+
+	// Verifying the proofs is paid for
+	let small = insert_did_document_unsigned(&key_pair, b"{}", &did_uri, 7, 0);
+	assert!(call.get_dispatch_info().weight > small.get_dispatch_info().weight);
+	assert!(small.get_dispatch_info().weight > 0);
+}
+
+#[rstest]
+fn verify_signatures_by_did_keys(
+	key_pair: KeyPair,
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	public_key: Vec<u8>,
+) {
+	let key = key_pair.public().encode();
+	let message = b"challenge".to_vec();
+	let signature = key_pair.sign(&message).encode();
+
+	new_test_ext().execute_with(|| {
+		assert!(!DIDModule::is_active(&did_uri));
+		assert!(!DIDModule::verify_did_signature(&did_uri, &key, &message, &signature));
+
+		DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			None,
+			None,
+			public_key,
+			did_uri.clone(),
+			None,
+			vec![did_signature(&key_pair, did_document.as_bytes())],
+		)
+		.ok();
+
+		assert!(DIDModule::is_active(&did_uri));
+		assert!(DIDModule::verify_did_signature(&did_uri, &key, &message, &signature));
+		assert!(!DIDModule::verify_did_signature(&did_uri, &key, b"other", &signature));
+		assert!(!DIDModule::verify_did_signature(&did_uri, &key, &message, &signature[1..]));
+		assert!(!DIDModule::verify_did_signature(&did_uri, &[0; 32], &message, &signature));
+
+		DIDModule::revoke_did(Origin::signed(1), did_uri.clone(), None).ok();
+		assert!(!DIDModule::is_active(&did_uri));
+		assert!(!DIDModule::verify_did_signature(&did_uri, &key, &message, &signature));
+	});
+}
+
+#[rstest]
+fn report_credential_status(
+	did_document: &'static str,
+	did_uri: Vec<u8>,
+	controller: Vec<u8>,
+	public_key: Vec<u8>,
+	signature: Vec<DIDSignature>,
+	credential_type: Vec<u8>,
+	vc_hash: Vec<u8>,
+) {
+	new_test_ext().execute_with(|| {
+		assert_eq!(DIDModule::vc_status(&vc_hash), None);
+
+		DIDModule::insert_did_document(
+			Origin::signed(1),
+			did_document.as_bytes().to_vec(),
+			None,
+			None,
+			controller,
+			did_uri.clone(),
+			None,
+			signature,
+		)
+		.ok();
+		DIDModule::accredit_issuer(Origin::root(), credential_type.clone(), did_uri.clone()).ok();
+		DIDModule::create_vc_fingerprint(
+			Origin::signed(1),
+			public_key,
+			vc_hash.clone(),
+			Some(true),
+			None,
+			Some(did_uri.clone()),
+			Some(credential_type.clone()),
+		)
+		.ok();
+
+		assert_eq!(
+			DIDModule::vc_status(&vc_hash),
+			Some(VerifiableCredentialStatus {
+				active: Some(true),
+				issuer_did_active: Some(true),
+				issuer_accredited: Some(true),
+				valid: true,
+			})
+		);
+
+		// Credentials of issuers losing their accreditation or DID are no longer valid
+		DIDModule::revoke_accreditation(Origin::root(), credential_type, did_uri.clone()).ok();
+		DIDModule::revoke_did(Origin::signed(1), did_uri, None).ok();
+		assert_eq!(
+			DIDModule::vc_status(&vc_hash),
+			Some(VerifiableCredentialStatus {
+				active: Some(true),
+				issuer_did_active: Some(false),
+				issuer_accredited: Some(false),
+				valid: false,
+			})
+		);
+	});
+}
@@ -1,9 +1,286 @@
 // This file is part of TrackBck which is released under GNU General Public License v3.0.
 // See file LICENSE.md or go to https://www.gnu.org/licenses/gpl-3.0.en.html for full license details.
 /// Utility methods to support DID files
+use sp_io::hashing::blake2_256;
+use sp_std::vec::Vec;
 
 pub trait CheckAuthorisation {}
 
 pub trait CheckAuthentication {}
 
 pub trait CheckExpiry {}
+
+/// Merkle tree over verifiable credential hashes
+/// * Leaves are `blake2_256(vc_hash)`
+/// * Parents are `blake2_256` of both children in ascending order, so proofs carry no positions
+/// * An unpaired node is promoted to the next level as is
+pub mod merkle {
+	use super::*;
+
+	/// Prefix of hashed leaves, so inner nodes can not be presented as credentials
+	const LEAF: u8 = 0x00;
+
+	/// Prefix of hashed inner nodes
+	const NODE: u8 = 0x01;
+
+	/// Hashes a 32 byte verifiable credential hash into a leaf
+	pub fn leaf(vc_hash: &[u8]) -> Option<[u8; 32]> {
+		if vc_hash.len() != 32 {
+			return None
+		}
+		let mut input = [LEAF; 33];
+		input[1..].copy_from_slice(vc_hash);
+		Some(blake2_256(&input))
+	}
+
+	/// Hashes two nodes into their parent
+	pub fn parent(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+		let (first, second) = if a <= b { (a, b) } else { (b, a) };
+		let mut input = [NODE; 65];
+		input[1..33].copy_from_slice(first);
+		input[33..].copy_from_slice(second);
+		blake2_256(&input)
+	}
+
+	/// Height of a tree over `leaf_count` leaves, the longest proof it has
+	pub fn max_proof_len(leaf_count: u32) -> usize {
+		if leaf_count <= 1 {
+			0
+		} else {
+			(32 - (leaf_count - 1).leading_zeros()) as usize
+		}
+	}
+
+	fn leaves(vc_hashes: &[Vec<u8>]) -> Option<Vec<[u8; 32]>> {
+		vc_hashes.iter().map(|h| leaf(h)).collect()
+	}
+
+	/// Computes the root over verifiable credential hashes
+	pub fn root(vc_hashes: &[Vec<u8>]) -> Option<[u8; 32]> {
+		let mut level = leaves(vc_hashes)?;
+		if level.is_empty() {
+			return None
+		}
+		while level.len() > 1 {
+			level = level
+				.chunks(2)
+				.map(|pair| if pair.len() == 2 { parent(&pair[0], &pair[1]) } else { pair[0] })
+				.collect();
+		}
+		Some(level[0])
+	}
+
+	/// Builds the inclusion proof of the verifiable credential hash at `index`
+	pub fn proof(vc_hashes: &[Vec<u8>], mut index: usize) -> Option<Vec<[u8; 32]>> {
+		let mut level = leaves(vc_hashes)?;
+		if index >= level.len() {
+			return None
+		}
+		let mut proof = Vec::new();
+		while level.len() > 1 {
+			let sibling = index ^ 1;
+			if sibling < level.len() {
+				proof.push(level[sibling]);
+			}
+			level = level
+				.chunks(2)
+				.map(|pair| if pair.len() == 2 { parent(&pair[0], &pair[1]) } else { pair[0] })
+				.collect();
+			index /= 2;
+		}
+		Some(proof)
+	}
+
+	/// Verifies a verifiable credential hash is included under the `root` of `leaf_count` leaves
+	pub fn verify(root: &[u8], vc_hash: &[u8], proof: &[Vec<u8>], leaf_count: u32) -> bool {
+		if proof.len() > max_proof_len(leaf_count) {
+			return false
+		}
+		let mut node = match leaf(vc_hash) {
+			| Some(node) => node,
+			| None => return false,
+		};
+		for sibling in proof {
+			if sibling.len() != 32 {
+				return false
+			}
+			let mut hash = [0u8; 32];
+			hash.copy_from_slice(sibling);
+			node = parent(&node, &hash);
+		}
+		&node[..] == root
+	}
+}
+
+/// Content identifiers of DID documents referenced by `did_ref`
+/// * CIDv0 (base58btc `Qm...`) and CIDv1 (multibase base32 `b...`), optionally `ipfs://` prefixed
+/// * Codecs `raw`, and `dag-pb` single block UnixFS files as written by `ipfs add`
+/// * Multihashes `sha2-256` and `blake2b-256`
+pub mod cid {
+	use super::*;
+	use sp_io::hashing::sha2_256;
+	use sp_std::vec;
+
+	/// Multicodec of raw bytes
+	pub const RAW: u64 = 0x55;
+
+	/// Multicodec of MerkleDAG protobuf nodes
+	pub const DAG_PB: u64 = 0x70;
+
+	/// Multihash code of sha2-256
+	pub const SHA2_256: u64 = 0x12;
+
+	/// Multihash code of blake2b-256
+	pub const BLAKE2B_256: u64 = 0xb220;
+
+	/// Parsed content identifier
+	#[derive(Clone, Debug, Eq, PartialEq)]
+	pub struct Cid {
+		pub codec: u64,
+		pub hash_code: u64,
+		pub digest: Vec<u8>,
+	}
+
+	/// Strips the optional `ipfs://` scheme of a DID reference
+	pub fn strip_scheme(did_ref: &[u8]) -> &[u8] {
+		did_ref.strip_prefix(b"ipfs://").unwrap_or(did_ref)
+	}
+
+	impl Cid {
+		/// Parses a supported content identifier
+		pub fn parse(did_ref: &[u8]) -> Option<Self> {
+			let did_ref = strip_scheme(did_ref);
+
+			let cid = if did_ref.len() == 46 && did_ref.starts_with(b"Qm") {
+				let multihash = base58_decode(did_ref)?;
+				let mut multihash = &multihash[..];
+				let hash_code = read_varint(&mut multihash)?;
+				let length = read_varint(&mut multihash)?;
+				if length as usize != multihash.len() {
+					return None
+				}
+				Cid { codec: DAG_PB, hash_code, digest: multihash.to_vec() }
+			} else {
+				let bytes = base32_decode(did_ref.strip_prefix(b"b")?)?;
+				let mut bytes = &bytes[..];
+				if read_varint(&mut bytes)? != 1 {
+					return None
+				}
+				let codec = read_varint(&mut bytes)?;
+				let hash_code = read_varint(&mut bytes)?;
+				let length = read_varint(&mut bytes)?;
+				if length as usize != bytes.len() {
+					return None
+				}
+				Cid { codec, hash_code, digest: bytes.to_vec() }
+			};
+
+			let supported = (cid.codec == RAW || cid.codec == DAG_PB) &&
+				(cid.hash_code == SHA2_256 || cid.hash_code == BLAKE2B_256) &&
+				cid.digest.len() == 32;
+			if supported {
+				Some(cid)
+			} else {
+				None
+			}
+		}
+
+		/// Checks the content identifier addresses the document
+		pub fn matches(&self, document: &[u8]) -> bool {
+			let block = match self.codec {
+				| RAW => document.to_vec(),
+				| DAG_PB => unixfs_file(document),
+				| _ => return false,
+			};
+			let digest = match self.hash_code {
+				| SHA2_256 => sha2_256(&block),
+				| BLAKE2B_256 => blake2_256(&block),
+				| _ => return false,
+			};
+			self.digest[..] == digest[..]
+		}
+	}
+
+	/// Wraps a document into the `dag-pb` node `ipfs add` writes for a single block file
+	fn unixfs_file(document: &[u8]) -> Vec<u8> {
+		// UnixFS Data { Type: File, Data, filesize }
+		let mut unixfs = vec![0x08, 0x02];
+		if !document.is_empty() {
+			unixfs.push(0x12);
+			write_varint(document.len() as u64, &mut unixfs);
+			unixfs.extend_from_slice(document);
+		}
+		unixfs.push(0x18);
+		write_varint(document.len() as u64, &mut unixfs);
+
+		// PBNode { Data }
+		let mut node = vec![0x0a];
+		write_varint(unixfs.len() as u64, &mut node);
+		node.extend_from_slice(&unixfs);
+		node
+	}
+
+	fn read_varint(bytes: &mut &[u8]) -> Option<u64> {
+		let mut value = 0u64;
+		for shift in (0..64).step_by(7) {
+			let (byte, rest) = bytes.split_first()?;
+			*bytes = rest;
+			value |= ((byte & 0x7f) as u64) << shift;
+			if byte & 0x80 == 0 {
+				return Some(value)
+			}
+		}
+		None
+	}
+
+	fn write_varint(mut value: u64, out: &mut Vec<u8>) {
+		while value >= 0x80 {
+			out.push((value as u8 & 0x7f) | 0x80);
+			value >>= 7;
+		}
+		out.push(value as u8);
+	}
+
+	fn base58_decode(input: &[u8]) -> Option<Vec<u8>> {
+		const ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+		// Little endian while decoding
+		let mut bytes: Vec<u8> = Vec::new();
+		for c in input {
+			let mut carry = ALPHABET.iter().position(|a| a == c)? as u32;
+			for byte in bytes.iter_mut() {
+				carry += *byte as u32 * 58;
+				*byte = carry as u8;
+				carry >>= 8;
+			}
+			while carry > 0 {
+				bytes.push(carry as u8);
+				carry >>= 8;
+			}
+		}
+		bytes.extend(input.iter().take_while(|c| **c == b'1').map(|_| 0));
+		bytes.reverse();
+		Some(bytes)
+	}
+
+	fn base32_decode(input: &[u8]) -> Option<Vec<u8>> {
+		let mut bytes = Vec::new();
+		let mut buffer = 0u32;
+		let mut bits = 0;
+		for c in input {
+			let value = match c {
+				| b'a'..=b'z' => c - b'a',
+				| b'2'..=b'7' => c - b'2' + 26,
+				| _ => return None,
+			};
+			buffer = (buffer << 5) | value as u32;
+			bits += 5;
+			if bits >= 8 {
+				bits -= 8;
+				bytes.push((buffer >> bits) as u8);
+				buffer &= (1 << bits) - 1;
+			}
+		}
+		Some(bytes)
+	}
+}
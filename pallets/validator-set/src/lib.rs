@@ -0,0 +1,207 @@
+// This file is part of TrackBck which is released under GNU General Public License v3.0.
+// See file LICENSE.md or go to https://www.gnu.org/licenses/gpl-3.0.en.html for full license details.
+//! TrackBack limited
+//! Permissioned Validator Set Pallet Implementation TrackBack Limited
+//! * Keeps the accounts allowed to author and finalise blocks
+//! * Adds and removes validators with `AddRemoveOrigin`, e.g. the council
+//! * Hands the set to `pallet_session` as its `SessionManager`, so changes apply at the next
+//!   session boundary without a hard fork
+//!
+//! # Storage
+//! ## Validators
+//! Accounts of the validator set
+//! * Value -> Validators' AccountIds
+//!
+//! # Sessions
+//! * Validators register Aura and Grandpa keys with `session.setKeys`
+//! * The set is handed over every session, so validators left out for lack of keys join once they
+//!   set them
+//! * With `pallet_session::historical` the set is also handed over with each validator's account as
+//!   its full identification, so Grandpa equivocations can be proven and reported as offences
+//! * A change made during session `n` is queued at its end, and authorities switch at the start of
+//!   session `n + 2`, as `pallet_session` always queues one session ahead
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+pub use pallet::*;
+
+#[frame_support::pallet]
+pub mod pallet {
+
+	use frame_support::{dispatch::DispatchResultWithPostInfo, pallet_prelude::*};
+	use frame_system::pallet_prelude::*;
+	use sp_runtime::traits::Convert;
+	use sp_std::{marker::PhantomData, vec::Vec};
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+
+		/// Origin which adds and removes validators, e.g. sudo or a council
+		type AddRemoveOrigin: EnsureOrigin<Self::Origin>;
+
+		/// Number of validators the set may not shrink below
+		#[pallet::constant]
+		type MinValidators: Get<u32>;
+	}
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	/// Accounts of the validator set
+	#[pallet::storage]
+	#[pallet::getter(fn validators)]
+	pub type Validators<T: Config> = StorageValue<_, Vec<T::AccountId>, ValueQuery>;
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig<T: Config> {
+		pub validators: Vec<T::AccountId>,
+	}
+
+	#[cfg(feature = "std")]
+	impl<T: Config> Default for GenesisConfig<T> {
+		fn default() -> Self {
+			Self { validators: vec![] }
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
+		fn build(&self) {
+			let mut validators = self.validators.clone();
+			validators.sort();
+			validators.dedup();
+			assert_eq!(
+				validators.len(),
+				self.validators.len(),
+				"Genesis validators must be unique"
+			);
+
+			// Handed to `pallet_session` as it builds its genesis, which must come after this one
+			Validators::<T>::put(&self.validators);
+		}
+	}
+
+	/// # Pallet Events
+	/// * ValidatorAdded / ValidatorRemoved
+	/// - Returns the validator's Account, the change applies from the next session boundary
+	#[pallet::event]
+	#[pallet::metadata(T::AccountId = "AccountId")]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// Validator added to the set
+		ValidatorAdded(T::AccountId),
+
+		/// Validator removed from the set
+		ValidatorRemoved(T::AccountId),
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// Account is already a validator
+		ValidatorExists,
+
+		/// Account is not a validator
+		ValidatorDoesNotExist,
+
+		/// Removing the validator would shrink the set below `MinValidators`
+		TooFewValidators,
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Adds a validator, it authors blocks once its session keys are set and the session
+		/// rotates
+		#[pallet::weight(0)]
+		pub fn add_validator(
+			origin: OriginFor<T>,
+			validator: T::AccountId,
+		) -> DispatchResultWithPostInfo {
+			T::AddRemoveOrigin::ensure_origin(origin)?;
+
+			Validators::<T>::try_mutate(|validators| {
+				ensure!(!validators.contains(&validator), Error::<T>::ValidatorExists);
+				validators.push(validator.clone());
+				Ok::<_, Error<T>>(())
+			})?;
+
+			Self::deposit_event(Event::ValidatorAdded(validator));
+
+			Ok(().into())
+		}
+
+		/// Removes a validator, it stops authoring blocks once the session rotates
+		#[pallet::weight(0)]
+		pub fn remove_validator(
+			origin: OriginFor<T>,
+			validator: T::AccountId,
+		) -> DispatchResultWithPostInfo {
+			T::AddRemoveOrigin::ensure_origin(origin)?;
+
+			Validators::<T>::try_mutate(|validators| {
+				let index = validators
+					.iter()
+					.position(|v| v == &validator)
+					.ok_or(Error::<T>::ValidatorDoesNotExist)?;
+				ensure!(
+					validators.len() > T::MinValidators::get() as usize,
+					Error::<T>::TooFewValidators
+				);
+				validators.remove(index);
+				Ok::<_, Error<T>>(())
+			})?;
+
+			Self::deposit_event(Event::ValidatorRemoved(validator));
+
+			Ok(().into())
+		}
+	}
+
+	/// Hands the validator set to `pallet_session` every session, as `pallet_session` leaves out
+	/// validators without keys, validators setting their keys late join at the next rotation
+	impl<T: Config> pallet_session::SessionManager<T::AccountId> for Pallet<T> {
+		fn new_session(_new_index: u32) -> Option<Vec<T::AccountId>> {
+			Some(Self::validators())
+		}
+
+		fn end_session(_end_index: u32) {}
+
+		fn start_session(_start_index: u32) {}
+	}
+
+	/// Hands the validator set to `pallet_session::historical::NoteHistoricalRoot`, validators are
+	/// fully identified by their account
+	impl<T: Config> pallet_session::historical::SessionManager<T::AccountId, T::AccountId>
+		for Pallet<T>
+	{
+		fn new_session(new_index: u32) -> Option<Vec<(T::AccountId, T::AccountId)>> {
+			<Self as pallet_session::SessionManager<_>>::new_session(new_index).map(|validators| {
+				validators.into_iter().map(|validator| (validator.clone(), validator)).collect()
+			})
+		}
+
+		fn end_session(_end_index: u32) {}
+
+		fn start_session(_start_index: u32) {}
+	}
+
+	/// Validators are identified by their account, `pallet_session::Config::ValidatorIdOf` and
+	/// `pallet_session::historical::Config::FullIdentificationOf`
+	pub struct ValidatorOf<T>(PhantomData<T>);
+
+	impl<T: Config> Convert<T::AccountId, Option<T::AccountId>> for ValidatorOf<T> {
+		fn convert(account: T::AccountId) -> Option<T::AccountId> {
+			Some(account)
+		}
+	}
+}
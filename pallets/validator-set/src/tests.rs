@@ -0,0 +1,68 @@
+// This file is part of TrackBck which is released under GNU General Public License v3.0.
+// See file LICENSE.md or go to https://www.gnu.org/licenses/gpl-3.0.en.html for full license details.
+use crate::mock::{new_test_ext, Origin, ValidatorSet};
+
+use frame_support::{assert_err, assert_ok, dispatch::DispatchError};
+use pallet_session::{historical, SessionManager};
+use sp_runtime::traits::BadOrigin;
+
+#[test]
+fn genesis_validators_start_the_first_session() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(ValidatorSet::new_session(0), Some(vec![1, 2, 3]));
+		assert_eq!(ValidatorSet::new_session(1), Some(vec![1, 2, 3]));
+	});
+}
+
+#[test]
+fn add_validator() {
+	new_test_ext().execute_with(|| {
+		ValidatorSet::new_session(0);
+
+		assert_err!(ValidatorSet::add_validator(Origin::signed(1), 4), BadOrigin);
+		assert_ok!(ValidatorSet::add_validator(Origin::root(), 4));
+		assert_err!(
+			ValidatorSet::add_validator(Origin::root(), 4),
+			DispatchError::Module { index: 1, error: 0, message: Some("ValidatorExists") }
+		);
+
+		assert_eq!(ValidatorSet::new_session(1), Some(vec![1, 2, 3, 4]));
+		assert_eq!(ValidatorSet::new_session(2), Some(vec![1, 2, 3, 4]));
+	});
+}
+
+#[test]
+fn remove_validator() {
+	new_test_ext().execute_with(|| {
+		ValidatorSet::new_session(0);
+
+		assert_err!(ValidatorSet::remove_validator(Origin::signed(1), 3), BadOrigin);
+		assert_err!(
+			ValidatorSet::remove_validator(Origin::root(), 4),
+			DispatchError::Module { index: 1, error: 1, message: Some("ValidatorDoesNotExist") }
+		);
+		assert_ok!(ValidatorSet::remove_validator(Origin::root(), 2));
+		assert_eq!(ValidatorSet::new_session(1), Some(vec![1, 3]));
+
+		// The set does not shrink below `MinValidators`
+		assert_err!(
+			ValidatorSet::remove_validator(Origin::root(), 3),
+			DispatchError::Module { index: 1, error: 2, message: Some("TooFewValidators") }
+		);
+		assert_eq!(ValidatorSet::new_session(2), Some(vec![1, 3]));
+	});
+}
+
+#[test]
+fn identify_validators_by_account_in_historical_sessions() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(
+			<ValidatorSet as historical::SessionManager<_, _>>::new_session(0),
+			Some(vec![(1, 1), (2, 2), (3, 3)])
+		);
+		assert_eq!(
+			<ValidatorSet as historical::SessionManager<_, _>>::new_session(1),
+			Some(vec![(1, 1), (2, 2), (3, 3)])
+		);
+	});
+}
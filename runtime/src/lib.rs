@@ -8,17 +8,27 @@
 #[cfg(feature = "std")]
 include!(concat!(env!("OUT_DIR"), "/wasm_binary.rs"));
 
-use frame_system::EnsureRoot;
+use frame_system::{EnsureOneOf, EnsureRoot};
 use pallet_grandpa::{
 	fg_primitives, AuthorityId as GrandpaId, AuthorityList as GrandpaAuthorityList,
 };
+use pallet_session::historical as pallet_session_historical;
 use sp_api::impl_runtime_apis;
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
-use sp_core::{crypto::KeyTypeId, OpaqueMetadata};
+use sp_core::{
+	crypto::KeyTypeId,
+	u32_trait::{_1, _2, _3, _4},
+	OpaqueMetadata,
+};
 use sp_runtime::{
 	create_runtime_str, generic, impl_opaque_keys,
-	traits::{AccountIdLookup, BlakeTwo256, Block as BlockT, IdentifyAccount, NumberFor, Verify},
-	transaction_validity::{TransactionSource, TransactionValidity},
+	traits::{
+		AccountIdLookup, BlakeTwo256, Block as BlockT, IdentifyAccount, NumberFor, OpaqueKeys,
+		Verify,
+	},
+	transaction_validity::{
+		TransactionLongevity, TransactionPriority, TransactionSource, TransactionValidity,
+	},
 	ApplyExtrinsicResult, MultiSignature,
 };
 use sp_std::prelude::*;
@@ -29,7 +39,7 @@ use sp_version::RuntimeVersion;
 // A few exports that help ease life for downstream crates.
 pub use frame_support::{
 	construct_runtime, parameter_types,
-	traits::{KeyOwnerProofSystem, Randomness},
+	traits::{Get, KeyOwnerProofSystem, Randomness},
 	weights::{
 		constants::{BlockExecutionWeight, ExtrinsicBaseWeight, RocksDbWeight, WEIGHT_PER_SECOND},
 		IdentityFee, Weight,
@@ -37,8 +47,9 @@ pub use frame_support::{
 	StorageValue,
 };
 pub use pallet_balances::Call as BalancesCall;
+pub use pallet_node_authorization::Call as NodeAuthorizationCall;
+pub use pallet_sudo::Call as SudoCall;
 pub use pallet_timestamp::Call as TimestampCall;
-use pallet_transaction_payment::CurrencyAdapter;
 #[cfg(any(feature = "std", test))]
 pub use sp_runtime::BuildStorage;
 pub use sp_runtime::{Perbill, Permill};
@@ -99,10 +110,10 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	spec_name: create_runtime_str!("trackback-node"),
 	impl_name: create_runtime_str!("trackback-node"),
 	authoring_version: 1,
-	spec_version: 100,
+	spec_version: 101,
 	impl_version: 1,
 	apis: RUNTIME_API_VERSIONS,
-	transaction_version: 1,
+	transaction_version: 2,
 };
 
 /// This determines the average expected block time that we are targeting.
@@ -191,6 +202,11 @@ impl frame_system::Config for Runtime {
 	type SS58Prefix = SS58Prefix;
 }
 
+parameter_types! {
+	/// Equivocation reports stay valid in the pool for six sessions
+	pub const ReportLongevity: u64 = (6 * HOURS) as u64;
+}
+
 impl pallet_aura::Config for Runtime {
 	type AuthorityId = AuraId;
 }
@@ -200,7 +216,7 @@ impl pallet_grandpa::Config for Runtime {
 
 	type Call = Call;
 
-	type KeyOwnerProofSystem = ();
+	type KeyOwnerProofSystem = Historical;
 
 	type KeyOwnerProof =
 		<Self::KeyOwnerProofSystem as KeyOwnerProofSystem<(KeyTypeId, GrandpaId)>>::Proof;
@@ -210,7 +226,11 @@ impl pallet_grandpa::Config for Runtime {
 		GrandpaId,
 	)>>::IdentificationTuple;
 
-	type HandleEquivocation = ();
+	type HandleEquivocation = pallet_grandpa::EquivocationHandler<
+		Self::KeyOwnerIdentification,
+		Offences,
+		ReportLongevity,
+	>;
 
 	type WeightInfo = ();
 }
@@ -250,7 +270,7 @@ parameter_types! {
 }
 
 impl pallet_transaction_payment::Config for Runtime {
-	type OnChargeTransaction = CurrencyAdapter<Balances, ()>;
+	type OnChargeTransaction = pallet_dids::SponsoredCurrencyAdapter<()>;
 	type TransactionByteFee = TransactionByteFee;
 	type WeightToFee = IdentityFee<Balance>;
 	type FeeMultiplierUpdate = ();
@@ -261,9 +281,167 @@ impl pallet_sudo::Config for Runtime {
 	type Call = Call;
 }
 
+parameter_types! {
+	pub const CouncilMotionDuration: BlockNumber = 3 * DAYS;
+	pub const CouncilMaxProposals: u32 = 100;
+	pub const CouncilMaxMembers: u32 = 100;
+}
+
+type CouncilCollective = pallet_collective::Instance1;
+/// Calls of the council, e.g. proposing an admin call.
+pub type CouncilCall = pallet_collective::Call<Runtime, pallet_collective::Instance1>;
+impl pallet_collective::Config<CouncilCollective> for Runtime {
+	type Origin = Origin;
+	type Proposal = Call;
+	type Event = Event;
+	type MotionDuration = CouncilMotionDuration;
+	type MaxProposals = CouncilMaxProposals;
+	type MaxMembers = CouncilMaxMembers;
+	type DefaultVote = pallet_collective::PrimeDefaultVote;
+	type WeightInfo = pallet_collective::weights::SubstrateWeight<Runtime>;
+}
+
+/// Root, i.e. sudo or a referendum, or two thirds of the council
+/// Admin origin of node authorization and issuer accreditation
+pub type EnsureRootOrTwoThirdsCouncil = EnsureOneOf<
+	AccountId,
+	EnsureRoot<AccountId>,
+	pallet_collective::EnsureProportionAtLeast<_2, _3, AccountId, CouncilCollective>,
+>;
+
+parameter_types! {
+	pub MaximumSchedulerWeight: Weight = Perbill::from_percent(80) *
+		BlockWeights::get().max_block;
+	pub const MaxScheduledPerBlock: u32 = 50;
+}
+
+impl pallet_scheduler::Config for Runtime {
+	type Event = Event;
+	type Origin = Origin;
+	type PalletsOrigin = OriginCaller;
+	type Call = Call;
+	type MaximumWeight = MaximumSchedulerWeight;
+	type ScheduleOrigin = EnsureRoot<AccountId>;
+	type MaxScheduledPerBlock = MaxScheduledPerBlock;
+	type WeightInfo = pallet_scheduler::weights::SubstrateWeight<Runtime>;
+}
+
+parameter_types! {
+	pub const LaunchPeriod: BlockNumber = 7 * DAYS;
+	pub const VotingPeriod: BlockNumber = 7 * DAYS;
+	pub const FastTrackVotingPeriod: BlockNumber = 3 * HOURS;
+	pub const InstantAllowed: bool = true;
+	pub const MinimumDeposit: Balance = 1 << 40;
+	pub const EnactmentPeriod: BlockNumber = DAYS;
+	pub const CooloffPeriod: BlockNumber = 7 * DAYS;
+	/// A runtime upgrade preimage of 1 MiB reserves about as much as a proposal deposit
+	pub const PreimageByteDeposit: Balance = 1 << 20;
+	pub const MaxVotes: u32 = 100;
+	pub const MaxProposals: u32 = 100;
+}
+
+/// Referenda dispatch with the root origin, so runtime upgrades (`set_code`) pass through the
+/// council and token holders rather than the sudo key
+impl pallet_democracy::Config for Runtime {
+	type Proposal = Call;
+	type Event = Event;
+	type Currency = Balances;
+	type EnactmentPeriod = EnactmentPeriod;
+	type LaunchPeriod = LaunchPeriod;
+	type VotingPeriod = VotingPeriod;
+	type MinimumDeposit = MinimumDeposit;
+	/// A straight majority of the council can decide what their next motion is.
+	type ExternalOrigin =
+		pallet_collective::EnsureProportionAtLeast<_1, _2, AccountId, CouncilCollective>;
+	/// A super-majority can have the next scheduled referendum be a straight majority-carries vote.
+	type ExternalMajorityOrigin =
+		pallet_collective::EnsureProportionAtLeast<_3, _4, AccountId, CouncilCollective>;
+	/// A unanimous council can have the next scheduled referendum be a straight default-carries
+	/// (NTB) vote.
+	type ExternalDefaultOrigin =
+		pallet_collective::EnsureProportionAtLeast<_1, _1, AccountId, CouncilCollective>;
+	/// Two thirds of the council can have an ExternalMajority/ExternalDefault vote be tabled
+	/// immediately and with a shorter voting/enactment period.
+	type FastTrackOrigin =
+		pallet_collective::EnsureProportionAtLeast<_2, _3, AccountId, CouncilCollective>;
+	type InstantOrigin =
+		pallet_collective::EnsureProportionAtLeast<_1, _1, AccountId, CouncilCollective>;
+	type InstantAllowed = InstantAllowed;
+	type FastTrackVotingPeriod = FastTrackVotingPeriod;
+	/// To cancel a proposal which has been passed, 2/3 of the council must agree to it.
+	type CancellationOrigin =
+		pallet_collective::EnsureProportionAtLeast<_2, _3, AccountId, CouncilCollective>;
+	/// To cancel a proposal before it has been passed, the council must be unanimous or Root must
+	/// agree.
+	type CancelProposalOrigin = EnsureOneOf<
+		AccountId,
+		EnsureRoot<AccountId>,
+		pallet_collective::EnsureProportionAtLeast<_1, _1, AccountId, CouncilCollective>,
+	>;
+	type BlacklistOrigin = EnsureRoot<AccountId>;
+	/// Any single council member may veto a coming council proposal, however they can only do it
+	/// once and it lasts only for the cool-off period.
+	type VetoOrigin = pallet_collective::EnsureMember<AccountId, CouncilCollective>;
+	type CooloffPeriod = CooloffPeriod;
+	type PreimageByteDeposit = PreimageByteDeposit;
+	type OperationalPreimageOrigin = pallet_collective::EnsureMember<AccountId, CouncilCollective>;
+	type Slash = ();
+	type Scheduler = Scheduler;
+	type PalletsOrigin = OriginCaller;
+	type MaxVotes = MaxVotes;
+	type WeightInfo = pallet_democracy::weights::SubstrateWeight<Runtime>;
+	type MaxProposals = MaxProposals;
+}
+
+impl<C> frame_system::offchain::SendTransactionTypes<C> for Runtime
+where
+	Call: From<C>,
+{
+	type Extrinsic = UncheckedExtrinsic;
+	type OverarchingCall = Call;
+}
+
+parameter_types! {
+	pub const DIDUnsignedPriority: TransactionPriority = TransactionPriority::max_value() / 2;
+	pub const DIDRefCheckInterval: BlockNumber = 10 * MINUTES;
+	pub const UnsignedDIDPriority: TransactionPriority = TransactionPriority::max_value() / 4;
+	pub const UnsignedDIDLongevity: TransactionLongevity = (10 * MINUTES) as TransactionLongevity;
+	pub const FeelessDIDOperations: bool = true;
+	pub const DIDOperationEra: BlockNumber = HOURS;
+	pub const MaxDIDOperationsPerEra: u32 = 10;
+	pub const MaxUnsignedDIDSize: u32 = 16 * 1024;
+	pub const MaxUnsignedDIDsPerBlock: u32 = 20;
+}
+
+/// Aura authorities check DID references from their offchain workers
+pub struct AuraAuthorities;
+
+impl Get<Vec<AuraId>> for AuraAuthorities {
+	fn get() -> Vec<AuraId> {
+		Aura::authorities()
+	}
+}
+
 impl pallet_dids::Config for Runtime {
 	type Event = Event;
 	type TimeProvider = pallet_timestamp::Pallet<Runtime>;
+	type Currency = Balances;
+	type Origin = Origin;
+	type Call = Call;
+	type AccreditationOrigin = EnsureRootOrTwoThirdsCouncil;
+	// Schemas are registered by their author DID through `submit_did_call`
+	type SchemaOrigin = pallet_dids::EnsureDid;
+	type AuthorityId = AuraId;
+	type Authorities = AuraAuthorities;
+	type UnsignedPriority = DIDUnsignedPriority;
+	type UnsignedDIDPriority = UnsignedDIDPriority;
+	type UnsignedDIDLongevity = UnsignedDIDLongevity;
+	type DIDRefCheckInterval = DIDRefCheckInterval;
+	type FeelessDIDOperations = FeelessDIDOperations;
+	type DIDOperationEra = DIDOperationEra;
+	type MaxDIDOperationsPerEra = MaxDIDOperationsPerEra;
+	type MaxUnsignedDIDSize = MaxUnsignedDIDSize;
+	type MaxUnsignedDIDsPerBlock = MaxUnsignedDIDsPerBlock;
 }
 
 parameter_types! {
@@ -275,13 +453,63 @@ impl pallet_node_authorization::Config for Runtime {
 	type Event = Event;
 	type MaxWellKnownNodes = MaxWellKnownNodes;
 	type MaxPeerIdLength = MaxPeerIdLength;
-	type AddOrigin = EnsureRoot<AccountId>;
-	type RemoveOrigin = EnsureRoot<AccountId>;
-	type SwapOrigin = EnsureRoot<AccountId>;
-	type ResetOrigin = EnsureRoot<AccountId>;
+	type AddOrigin = EnsureRootOrTwoThirdsCouncil;
+	type RemoveOrigin = EnsureRootOrTwoThirdsCouncil;
+	type SwapOrigin = EnsureRootOrTwoThirdsCouncil;
+	type ResetOrigin = EnsureRootOrTwoThirdsCouncil;
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub const MinValidators: u32 = 1;
+}
+
+impl pallet_validator_set::Config for Runtime {
+	type Event = Event;
+	type AddRemoveOrigin = EnsureRootOrTwoThirdsCouncil;
+	type MinValidators = MinValidators;
+}
+
+parameter_types! {
+	pub const SessionPeriod: BlockNumber = HOURS;
+	pub const SessionOffset: BlockNumber = 0;
+	pub const DisabledValidatorsThreshold: Perbill = Perbill::from_percent(17);
+}
+
+/// Sessions hand Aura and Grandpa the authorities of the validator set
+impl pallet_session::Config for Runtime {
+	type Event = Event;
+	type ValidatorId = <Self as frame_system::Config>::AccountId;
+	type ValidatorIdOf = pallet_validator_set::ValidatorOf<Self>;
+	type ShouldEndSession = pallet_session::PeriodicSessions<SessionPeriod, SessionOffset>;
+	type NextSessionRotation = pallet_session::PeriodicSessions<SessionPeriod, SessionOffset>;
+	type SessionManager = pallet_session::historical::NoteHistoricalRoot<Self, ValidatorSet>;
+	type SessionHandler = <opaque::SessionKeys as OpaqueKeys>::KeyTypeIdProviders;
+	type Keys = opaque::SessionKeys;
+	type DisabledValidatorsThreshold = DisabledValidatorsThreshold;
 	type WeightInfo = ();
 }
 
+/// Keeps the validator set of past sessions, Grandpa key ownership proofs are checked against it
+impl pallet_session::historical::Config for Runtime {
+	type FullIdentification = AccountId;
+	type FullIdentificationOf = pallet_validator_set::ValidatorOf<Self>;
+}
+
+parameter_types! {
+	pub OffencesWeightSoftLimit: Weight = Perbill::from_percent(60) *
+		BlockWeights::get().max_block;
+}
+
+/// Records offences of validators, e.g. Grandpa equivocations, without slashing as there is no
+/// staking, governance removes offenders from the validator set
+impl pallet_offences::Config for Runtime {
+	type Event = Event;
+	type IdentificationTuple = pallet_session::historical::IdentificationTuple<Self>;
+	type OnOffenceHandler = ();
+	type WeightSoftLimit = OffencesWeightSoftLimit;
+}
+
 // Create the runtime by composing the FRAME pallets that were previously configured.
 construct_runtime!(
 	pub enum Runtime where
@@ -293,13 +521,22 @@ construct_runtime!(
 		RandomnessCollectiveFlip: pallet_randomness_collective_flip::{Module, Call, Storage},
 		Timestamp: pallet_timestamp::{Module, Call, Storage, Inherent},
 		Aura: pallet_aura::{Module, Config<T>},
-		Grandpa: pallet_grandpa::{Module, Call, Storage, Config, Event},
+		Grandpa: pallet_grandpa::{Module, Call, Storage, Config, Event, ValidateUnsigned},
 		Balances: pallet_balances::{Module, Call, Storage, Config<T>, Event<T>},
 		TransactionPayment: pallet_transaction_payment::{Module, Storage},
 		Sudo: pallet_sudo::{Module, Call, Config<T>, Storage, Event<T>},
 		NodeAuthorization: pallet_node_authorization::{Module, Call, Storage, Event<T>, Config<T>},
 		// Include the custom logic from the template pallet in the runtime.
-		DIDModule: pallet_dids::{Module, Call, Storage, Event<T>},
+		DIDModule: pallet_dids::{Module, Call, Storage, Event<T>, Origin, Config<T>, ValidateUnsigned},
+		// Governance: council motions and referenda replace the sudo key for upgrades and admin calls
+		Council: pallet_collective::<Instance1>::{Module, Call, Storage, Origin<T>, Event<T>, Config<T>},
+		Scheduler: pallet_scheduler::{Module, Call, Storage, Event<T>},
+		Democracy: pallet_democracy::{Module, Call, Storage, Config, Event<T>},
+		// Validators set up at genesis before sessions, which hand their keys to Aura and Grandpa
+		ValidatorSet: pallet_validator_set::{Module, Call, Storage, Event<T>, Config<T>},
+		Session: pallet_session::{Module, Call, Storage, Event, Config<T>},
+		Historical: pallet_session_historical::{Module},
+		Offences: pallet_offences::{Module, Call, Storage, Event},
 	}
 );
 
@@ -322,6 +559,8 @@ pub type SignedExtra = (
 	frame_system::CheckNonce<Runtime>,
 	frame_system::CheckWeight<Runtime>,
 	pallet_transaction_payment::ChargeTransactionPayment<Runtime>,
+	pallet_dids::CheckDidAuthorization<Runtime>,
+	pallet_dids::CheckDidOperationLimits<Runtime>,
 );
 
 /// Unchecked extrinsic type as expected by this runtime.
@@ -427,23 +666,29 @@ impl_runtime_apis! {
 		}
 
 		fn submit_report_equivocation_unsigned_extrinsic(
-			_equivocation_proof: fg_primitives::EquivocationProof<
+			equivocation_proof: fg_primitives::EquivocationProof<
 				<Block as BlockT>::Hash,
 				NumberFor<Block>,
 			>,
-			_key_owner_proof: fg_primitives::OpaqueKeyOwnershipProof,
+			key_owner_proof: fg_primitives::OpaqueKeyOwnershipProof,
 		) -> Option<()> {
-			None
+			let key_owner_proof = key_owner_proof.decode()?;
+
+			Grandpa::submit_unsigned_equivocation_report(
+				equivocation_proof,
+				key_owner_proof,
+			)
 		}
 
 		fn generate_key_ownership_proof(
 			_set_id: fg_primitives::SetId,
-			_authority_id: GrandpaId,
+			authority_id: GrandpaId,
 		) -> Option<fg_primitives::OpaqueKeyOwnershipProof> {
-			// NOTE: this is the only implementation possible since we've
-			// defined our key owner proof type as a bottom type (i.e. a type
-			// with no values).
-			None
+			use codec::Encode;
+
+			Historical::prove((fg_primitives::KEY_TYPE, authority_id))
+				.map(|p| p.encode())
+				.map(fg_primitives::OpaqueKeyOwnershipProof::new)
 		}
 	}
 
@@ -453,6 +698,92 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl pallet_dids_runtime_api::DIDApi<Block, AccountId> for Runtime {
+		fn vc_fingerprint(
+			vc_hash: Vec<u8>,
+		) -> Option<pallet_dids_runtime_api::CredentialFingerprint<AccountId>> {
+			DIDModule::get_verifiable_credential_hash(vc_hash).map(|vc| {
+				pallet_dids_runtime_api::CredentialFingerprint {
+					issuer: vc.issuer,
+					holder_public_key: vc.public_key,
+					active: vc.active,
+					block_time_stamp: vc.block_time_stamp,
+					schema_id: vc.schema_id,
+					issuer_did: vc.issuer_did,
+					credential_type: vc.credential_type,
+				}
+			})
+		}
+
+		fn trusted_issuers(credential_type: Vec<u8>) -> Vec<Vec<u8>> {
+			DIDModule::trusted_issuers(credential_type)
+		}
+
+		fn verify_batch_inclusion(
+			merkle_root: Vec<u8>,
+			vc_hash: Vec<u8>,
+			proof: Vec<Vec<u8>>,
+		) -> Option<pallet_dids_runtime_api::CredentialBatch<AccountId>> {
+			DIDModule::verify_batch_inclusion(merkle_root, vc_hash, proof).map(|batch| {
+				pallet_dids_runtime_api::CredentialBatch {
+					issuer: batch.issuer,
+					issuer_did: batch.issuer_did,
+					credential_type: batch.credential_type,
+					leaf_count: batch.leaf_count,
+					block_time_stamp: batch.block_time_stamp,
+				}
+			})
+		}
+
+		fn dids_by_controller(controller: AccountId) -> Vec<Vec<u8>> {
+			DIDModule::dids_by_controller(controller)
+		}
+
+		fn dids_by_public_key(public_key: Vec<u8>) -> Vec<Vec<u8>> {
+			DIDModule::dids_by_public_key(public_key)
+		}
+
+		fn resolve(did_uri: Vec<u8>) -> Option<pallet_dids_runtime_api::DIDRecord<AccountId>> {
+			DIDModule::get_did_document(&did_uri).map(|did| pallet_dids_runtime_api::DIDRecord {
+				controller: DIDModule::get_did_controller(&did_uri),
+				did_resolution_metadata: did.did_resolution_metadata,
+				did_document_metadata: did.did_document_metadata,
+				did_ref: did.did_ref,
+				did_document_hash: did.did_document_hash,
+				public_keys: DIDModule::get_signature(&did_uri)
+					.unwrap_or_default()
+					.into_iter()
+					.map(|signature| signature.public_key)
+					.collect(),
+				version: did.version,
+				block_time_stamp: did.block_time_stamp,
+				updated_time_stamp: did.updated_time_stamp,
+			})
+		}
+
+		fn is_active(did_uri: Vec<u8>) -> bool {
+			DIDModule::is_active(&did_uri)
+		}
+
+		fn verify(
+			did_uri: Vec<u8>,
+			public_key: Vec<u8>,
+			message: Vec<u8>,
+			signature: Vec<u8>,
+		) -> bool {
+			DIDModule::verify_did_signature(&did_uri, &public_key, &message, &signature)
+		}
+
+		fn vc_status(vc_hash: Vec<u8>) -> Option<pallet_dids_runtime_api::CredentialStatus> {
+			DIDModule::vc_status(&vc_hash).map(|status| pallet_dids_runtime_api::CredentialStatus {
+				active: status.active,
+				issuer_did_active: status.issuer_did_active,
+				issuer_accredited: status.issuer_accredited,
+				valid: status.valid,
+			})
+		}
+	}
+
 	impl pallet_transaction_payment_rpc_runtime_api::TransactionPaymentApi<Block, Balance>
 		for Runtime {
 		fn query_info(
@@ -469,6 +800,14 @@ impl_runtime_apis! {
 		}
 	}
 
+	#[cfg(feature = "try-runtime")]
+	impl frame_try_runtime::TryRuntime<Block> for Runtime {
+		fn on_runtime_upgrade() -> Result<(Weight, Weight), sp_runtime::RuntimeString> {
+			let weight = Executive::try_runtime_upgrade()?;
+			Ok((weight, BlockWeights::get().max_block))
+		}
+	}
+
 	#[cfg(feature = "runtime-benchmarks")]
 	impl frame_benchmarking::Benchmark<Block> for Runtime {
 		fn dispatch_benchmark(